@@ -1,15 +1,27 @@
 extern crate proc_macro;
 
 use proc_macro2::TokenStream;
-use quote::quote;
-use syn::{parse_quote, DeriveInput, Error, LitInt};
+use quote::{format_ident, quote};
+use syn::{parse_quote, Data, DeriveInput, Error, Fields, Ident, LitInt, WhereClause};
 
+/// Implements `Persist` by dispatching on a leading `u16` version to `PersistVersion::<0..=version>`.
+///
+/// With `#[persist(version = N, auto)]`, `PersistVersion::<N>` is also generated from the fields in
+/// declaration order. Enum variants then each need a stable `#[persist(tag = T)]`, written as a `u16`
+/// before the variant's fields. Omitting `version` with `auto` implements `Persist` directly from
+/// the fields without any version header, e.g. for the frozen shapes of older versions.
+///
+/// With `#[persist(migrate)]`, versions below `N` are instead read as `PersistMigrate::<i>::Legacy`
+/// and upgraded one version at a time up to `Self`. Each `PersistMigrate::<i>::Next` must be
+/// `PersistMigrate::<i + 1>::Legacy`, and the last one `Self`.
 #[proc_macro_derive(Persist, attributes(persist))]
 pub fn derive_persist(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     fn derive_persist(input: TokenStream) -> syn::Result<TokenStream> {
         let input = syn::parse2::<DeriveInput>(input)?;
         let name = input.ident;
         let mut version = None;
+        let mut auto = false;
+        let mut migrate = false;
 
         for attr in &input.attrs {
             if attr.path().is_ident("persist") {
@@ -17,6 +29,12 @@ pub fn derive_persist(input: proc_macro::TokenStream) -> proc_macro::TokenStream
                     if nested.path.is_ident("version") {
                         version = Some(nested.value()?.parse::<LitInt>()?.base10_parse::<u16>()?);
                         Ok(())
+                    } else if nested.path.is_ident("auto") {
+                        auto = true;
+                        Ok(())
+                    } else if nested.path.is_ident("migrate") {
+                        migrate = true;
+                        Ok(())
                     } else {
                         Err(nested.error("unsupported attribute"))
                     }
@@ -25,7 +43,59 @@ pub fn derive_persist(input: proc_macro::TokenStream) -> proc_macro::TokenStream
         }
 
         let Some(version) = version else {
-            return Err(Error::new_spanned(name, "missing `version = ...`"))
+            if !auto {
+                return Err(Error::new_spanned(name, "missing `version = ...`"))
+            }
+
+            if migrate {
+                return Err(Error::new_spanned(name, "`migrate` requires `version = ...`"))
+            }
+
+            let mut generics = input.generics;
+            let (r, w, read, write) = derive_fields(&name, &input.data, generics.make_where_clause())?;
+
+            let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+            return Ok(quote! {
+                impl #impl_generics crate::persist::Persist for #name #type_generics #where_clause {
+                    async fn read<R: ::bevy::tasks::futures_lite::AsyncRead + ::bevy::utils::ConditionalSend>(
+                        #r: ::std::pin::Pin<&mut crate::persist::PersistReader<R>>,
+                    ) -> ::std::io::Result<Self> {
+                        #read
+                    }
+
+                    async fn write<W: ::bevy::tasks::futures_lite::AsyncWrite + ::bevy::utils::ConditionalSend>(
+                        &self,
+                        #w: ::std::pin::Pin<&mut crate::persist::PersistWriter<W>>,
+                    ) -> ::std::io::Result<()> {
+                        #write
+                    }
+                }
+            })
+        };
+
+        let auto_impl = if auto {
+            let mut generics = input.generics.clone();
+            let (r, w, read, write) = derive_fields(&name, &input.data, generics.make_where_clause())?;
+
+            let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+            quote! {
+                impl #impl_generics crate::persist::PersistVersion::<#version> for #name #type_generics #where_clause {
+                    async fn read_versioned<R: ::bevy::tasks::futures_lite::AsyncRead + ::bevy::utils::ConditionalSend>(
+                        #r: ::std::pin::Pin<&mut crate::persist::PersistReader<R>>,
+                    ) -> ::std::io::Result<Self> {
+                        #read
+                    }
+
+                    async fn write_versioned<W: ::bevy::tasks::futures_lite::AsyncWrite + ::bevy::utils::ConditionalSend>(
+                        &self,
+                        #w: ::std::pin::Pin<&mut crate::persist::PersistWriter<W>>,
+                    ) -> ::std::io::Result<()> {
+                        #write
+                    }
+                }
+            }
+        } else {
+            TokenStream::new()
         };
 
         let mut generics = input.generics;
@@ -33,13 +103,41 @@ pub fn derive_persist(input: proc_macro::TokenStream) -> proc_macro::TokenStream
 
         let mut reads = Vec::with_capacity(version as usize + 1);
         for i in 0..=version {
-            clause.predicates.push(parse_quote! {
-                Self: crate::persist::PersistVersion::<#i>
-            });
+            if migrate && i < version {
+                // Each step has to upgrade into exactly what the next one starts from, ending at `Self`.
+                let next = i + 1;
+                clause.predicates.push(if next < version {
+                    parse_quote! {
+                        Self: crate::persist::PersistMigrate::<#i, Next = <Self as crate::persist::PersistMigrate::<#next>>::Legacy>
+                    }
+                } else {
+                    parse_quote! {
+                        Self: crate::persist::PersistMigrate::<#i, Next = Self>
+                    }
+                });
 
-            reads.push(quote! {
-                #i => <Self as crate::persist::PersistVersion::<#i>>::read_versioned(r).await,
-            });
+                let steps = (i..version).map(|step| {
+                    quote! {
+                        let value = <Self as crate::persist::PersistMigrate::<#step>>::migrate(value);
+                    }
+                });
+
+                reads.push(quote! {
+                    #i => {
+                        let value = <<Self as crate::persist::PersistMigrate::<#i>>::Legacy as crate::persist::Persist>::read(r).await?;
+                        #(#steps)*
+                        Ok(value)
+                    }
+                });
+            } else {
+                clause.predicates.push(parse_quote! {
+                    Self: crate::persist::PersistVersion::<#i>
+                });
+
+                reads.push(quote! {
+                    #i => <Self as crate::persist::PersistVersion::<#i>>::read_versioned(r).await,
+                });
+            }
         }
 
         let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
@@ -48,7 +146,7 @@ pub fn derive_persist(input: proc_macro::TokenStream) -> proc_macro::TokenStream
                 async fn read<R: ::bevy::tasks::futures_lite::AsyncRead + ::bevy::utils::ConditionalSend>(
                     mut r: ::std::pin::Pin<&mut crate::persist::PersistReader<R>>,
                 ) -> ::std::io::Result<Self> {
-                    match u16::read(r.as_mut()).await? {
+                    match <u16 as crate::persist::Persist>::read(r.as_mut()).await? {
                         #(#reads)*
                         v => Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, ::std::format!("Invalid version: {v}."))),
                     }
@@ -58,12 +156,178 @@ pub fn derive_persist(input: proc_macro::TokenStream) -> proc_macro::TokenStream
                     &self,
                     mut w: ::std::pin::Pin<&mut crate::persist::PersistWriter<W>>,
                 ) -> ::std::io::Result<()> {
-                    u16::write(&#version, w.as_mut()).await?;
+                    <u16 as crate::persist::Persist>::write(&#version, w.as_mut()).await?;
                     <Self as crate::persist::PersistVersion::<#version>>::write_versioned(self, w).await
                 }
             }
+
+            #auto_impl
         })
     }
 
     derive_persist(input.into()).unwrap_or_else(Error::into_compile_error).into()
 }
+
+/// Generates the reader and writer parameter patterns and bodies that read and write `data` field by
+/// field, adding a `Persist` bound on every field type to `clause`.
+fn derive_fields(
+    name: &Ident,
+    data: &Data,
+    clause: &mut WhereClause,
+) -> syn::Result<(TokenStream, TokenStream, TokenStream, TokenStream)> {
+    match data {
+        Data::Struct(data) => {
+            for field in &data.fields {
+                let ty = &field.ty;
+                clause.predicates.push(parse_quote! {
+                    #ty: crate::persist::Persist
+                });
+            }
+
+            let (r, w) = if data.fields.is_empty() {
+                (quote! { _r }, quote! { _w })
+            } else {
+                (quote! { mut r }, quote! { mut w })
+            };
+
+            let read = read_fields(quote! { Self }, &data.fields);
+            let (pattern, writes) = write_fields(quote! { Self }, &data.fields);
+            Ok((
+                r,
+                w,
+                quote! { ::std::result::Result::Ok(#read) },
+                quote! {
+                    let #pattern = self;
+                    #writes
+                    ::std::result::Result::Ok(())
+                },
+            ))
+        }
+        Data::Enum(data) => {
+            if data.variants.is_empty() {
+                return Err(Error::new_spanned(name, "`auto` is not supported for enums without variants"))
+            }
+
+            let mut tags = Vec::<u16>::with_capacity(data.variants.len());
+            let mut errors = None::<Error>;
+            let mut reads = Vec::with_capacity(data.variants.len());
+            let mut writes = Vec::with_capacity(data.variants.len());
+
+            for variant in &data.variants {
+                let mut tag = None;
+                for attr in &variant.attrs {
+                    if attr.path().is_ident("persist") {
+                        attr.parse_nested_meta(|nested| {
+                            if nested.path.is_ident("tag") {
+                                tag = Some(nested.value()?.parse::<LitInt>()?.base10_parse::<u16>()?);
+                                Ok(())
+                            } else {
+                                Err(nested.error("unsupported attribute"))
+                            }
+                        })?
+                    }
+                }
+
+                let ident = &variant.ident;
+                // Tags are never inferred from declaration order, so reordering variants can't change what's on disk.
+                let tag = match tag {
+                    Some(tag) if !tags.contains(&tag) => tag,
+                    tag => {
+                        let error = match tag {
+                            Some(tag) => Error::new_spanned(ident, format!("duplicate tag: {tag}")),
+                            None => Error::new_spanned(ident, "missing `tag = ...`"),
+                        };
+
+                        match &mut errors {
+                            Some(errors) => errors.combine(error),
+                            None => errors = Some(error),
+                        }
+                        continue
+                    }
+                };
+
+                tags.push(tag);
+
+                for field in &variant.fields {
+                    let ty = &field.ty;
+                    clause.predicates.push(parse_quote! {
+                        #ty: crate::persist::Persist
+                    });
+                }
+
+                let read = read_fields(quote! { Self::#ident }, &variant.fields);
+                reads.push(quote! {
+                    #tag => ::std::result::Result::Ok(#read),
+                });
+
+                let (pattern, write) = write_fields(quote! { Self::#ident }, &variant.fields);
+                writes.push(quote! {
+                    #pattern => {
+                        <u16 as crate::persist::Persist>::write(&#tag, w.as_mut()).await?;
+                        #write
+                    }
+                });
+            }
+
+            if let Some(errors) = errors {
+                return Err(errors)
+            }
+
+            Ok((
+                quote! { mut r },
+                quote! { mut w },
+                quote! {
+                    match <u16 as crate::persist::Persist>::read(r.as_mut()).await? {
+                        #(#reads)*
+                        t => Err(::std::io::Error::new(::std::io::ErrorKind::InvalidData, ::std::format!("Invalid tag: {t}."))),
+                    }
+                },
+                quote! {
+                    match self {
+                        #(#writes)*
+                    }
+
+                    ::std::result::Result::Ok(())
+                },
+            ))
+        }
+        Data::Union(..) => Err(Error::new_spanned(name, "`auto` is not supported for unions")),
+    }
+}
+
+fn read_fields(path: TokenStream, fields: &Fields) -> TokenStream {
+    let reads = fields.iter().map(|field| {
+        let ty = &field.ty;
+        quote! { <#ty as crate::persist::Persist>::read(r.as_mut()).await? }
+    });
+
+    match fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote! { #path { #(#names: #reads,)* } }
+        }
+        Fields::Unnamed(..) => quote! { #path(#(#reads,)*) },
+        Fields::Unit => path,
+    }
+}
+
+fn write_fields(path: TokenStream, fields: &Fields) -> (TokenStream, TokenStream) {
+    // Bind to generated names so fields named `w` don't shadow the writer.
+    let bindings = (0..fields.len()).map(|i| format_ident!("field_{i}")).collect::<Vec<_>>();
+
+    let writes = fields.iter().zip(&bindings).map(|(field, binding)| {
+        let ty = &field.ty;
+        quote! { <#ty as crate::persist::Persist>::write(#binding, w.as_mut()).await?; }
+    });
+
+    let pattern = match fields {
+        Fields::Named(fields) => {
+            let names = fields.named.iter().map(|field| &field.ident);
+            quote! { #path { #(#names: #bindings,)* } }
+        }
+        Fields::Unnamed(..) => quote! { #path(#(#bindings,)*) },
+        Fields::Unit => path,
+    };
+
+    (pattern, quote! { #(#writes)* })
+}
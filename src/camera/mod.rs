@@ -0,0 +1,334 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{LocalGravity, control::Move};
+
+#[derive(Component, Copy, Clone, Default, Debug)]
+#[require(Camera2d, CameraRig)]
+pub struct PrimaryCamera;
+
+/// Marks an entity for [`CameraRig`]s to follow. With several targets, rigs follow their average.
+#[derive(Component, Copy, Clone, Default, Debug)]
+pub struct CameraTarget;
+
+/// Keeps the camera within this area, e.g. the extents of the current level.
+#[derive(Component, Copy, Clone, Debug)]
+pub struct CameraBounds(pub Rect);
+
+/// Adds trauma to every [`CameraRig`], shaking it harder the more trauma it has. Trauma is capped
+/// at 1 and wears off over time.
+#[derive(Event, Copy, Clone, Debug)]
+pub struct CameraShake(pub f32);
+
+/// Smoothly follows [`CameraTarget`]s, looking ahead in the direction they [`Move`] and rotating so
+/// their [`LocalGravity`] points down the screen. Rates are in inverse seconds; higher catches up
+/// faster.
+#[derive(Component, Copy, Clone, Debug)]
+pub struct CameraRig {
+    pub follow_rate: f32,
+    /// Half-extents of the area, in the camera's own frame, targets can move in without it
+    /// following.
+    pub dead_zone: Vec2,
+    /// How far ahead of targets to look while they move.
+    pub look_ahead: f32,
+    pub look_ahead_rate: f32,
+    pub rotation_rate: f32,
+    /// Desired projection scale, clamped to [`min_zoom`](Self::min_zoom) and
+    /// [`max_zoom`](Self::max_zoom).
+    pub zoom: f32,
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+    pub zoom_rate: f32,
+    /// Largest offset, in world units, a full-trauma shake moves the camera by.
+    pub shake_offset: f32,
+    /// Largest angle, in radians, a full-trauma shake tilts the camera by.
+    pub shake_angle: f32,
+    /// Trauma worn off per second.
+    pub shake_decay: f32,
+    /// Point within the dead zone being followed, or `None` until there's a target to snap to.
+    focus: Option<Vec2>,
+    look: Vec2,
+    center: Vec2,
+    angle: f32,
+    trauma: f32,
+    shake_time: f32,
+}
+
+impl CameraRig {
+    pub fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    /// Where the camera looks at before shaking.
+    pub fn center(&self) -> Vec2 {
+        self.center
+    }
+
+    /// The camera's rotation, in radians, before shaking.
+    pub fn angle(&self) -> f32 {
+        self.angle
+    }
+
+    /// Jumps straight to the targets next frame instead of panning to them, e.g. after a level
+    /// change.
+    pub fn snap(&mut self) {
+        self.focus = None
+    }
+}
+
+impl Default for CameraRig {
+    fn default() -> Self {
+        Self {
+            follow_rate: 8.,
+            dead_zone: Vec2::new(48., 32.),
+            look_ahead: 64.,
+            look_ahead_rate: 3.,
+            rotation_rate: 6.,
+            zoom: 1.,
+            min_zoom: 0.5,
+            max_zoom: 2.,
+            zoom_rate: 4.,
+            shake_offset: 16.,
+            shake_angle: 0.05,
+            shake_decay: 1.5,
+            focus: None,
+            look: Vec2::ZERO,
+            center: Vec2::ZERO,
+            angle: 0.,
+            trauma: 0.,
+            shake_time: 0.,
+        }
+    }
+}
+
+pub struct CameraPlugin;
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CameraShake>().add_systems(
+            PostUpdate,
+            (add_trauma, follow_targets)
+                .chain()
+                .after(PhysicsSet::Sync)
+                .before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+fn add_trauma(mut shakes: EventReader<CameraShake>, mut rigs: Query<&mut CameraRig>) {
+    let trauma = shakes.read().map(|&CameraShake(trauma)| trauma).sum::<f32>();
+    if trauma > 0. {
+        for mut rig in &mut rigs {
+            rig.trauma = (rig.trauma + trauma).min(1.)
+        }
+    }
+}
+
+type TargetQuery<'a> = (&'a Transform, Option<&'a LocalGravity>, Option<&'a ActionState<Move>>);
+type RigQuery<'a> = (
+    &'a mut CameraRig,
+    &'a mut Transform,
+    &'a mut OrthographicProjection,
+    Option<&'a CameraBounds>,
+);
+
+fn follow_targets(
+    time: Res<Time>,
+    targets: Query<TargetQuery, (With<CameraTarget>, Without<CameraRig>)>,
+    mut rigs: Query<RigQuery>,
+) {
+    let dt = time.delta_secs();
+
+    let mut count = 0.;
+    let (mut position, mut up, mut look) = (Vec2::ZERO, Vec2::ZERO, Vec2::ZERO);
+    for (transform, gravity, movement) in &targets {
+        let (target_up, target_right) = gravity.map_or((Vec2::Y, Vec2::X), |gravity| (*gravity.up(), *gravity.right()));
+        let axis = movement.map_or(Vec2::ZERO, |state| state.clamped_axis_pair(&Move));
+
+        count += 1.;
+        position += transform.translation.truncate();
+        up += target_up;
+        look += target_right * axis.x + target_up * axis.y;
+    }
+
+    for (mut rig, mut transform, mut projection, bounds) in &mut rigs {
+        let rig = &mut *rig;
+        if count > 0. {
+            let position = position / count;
+            let look = (look / count).clamp_length_max(1.) * rig.look_ahead;
+            let angle = up.try_normalize().map_or(rig.angle, |up| up.to_angle() - FRAC_PI_2);
+
+            match rig.focus {
+                Some(ref mut focus) => {
+                    // Only follow whatever part of the movement leaves the dead zone, as seen from the camera.
+                    let rotation = Rot2::radians(rig.angle);
+                    let offset = rotation.inverse() * (position - *focus);
+                    let excess = offset - offset.clamp(-rig.dead_zone, rig.dead_zone);
+                    *focus += rotation * excess;
+
+                    rig.look = rig.look.lerp(look, smoothing(rig.look_ahead_rate, dt));
+                    rig.center = rig.center.lerp(*focus + rig.look, smoothing(rig.follow_rate, dt));
+                    rig.angle += wrap_angle(angle - rig.angle) * smoothing(rig.rotation_rate, dt)
+                }
+                None => {
+                    rig.focus = Some(position);
+                    rig.look = Vec2::ZERO;
+                    rig.center = position;
+                    rig.angle = angle
+                }
+            }
+        }
+
+        let zoom = rig.zoom.clamp(rig.min_zoom, rig.max_zoom);
+        projection.scale += (zoom - projection.scale) * smoothing(rig.zoom_rate, dt);
+
+        if let Some(&CameraBounds(bounds)) = bounds {
+            // The area the rotated viewport covers, which is updated from the scale later on in the frame.
+            let half = projection.area.half_size();
+            let (sin, cos) = rig.angle.sin_cos();
+            let extents = Vec2::new(
+                cos.abs() * half.x + sin.abs() * half.y,
+                sin.abs() * half.x + cos.abs() * half.y,
+            );
+
+            rig.center = clamp_within(rig.center, bounds, extents)
+        }
+
+        rig.shake_time += dt;
+        rig.trauma = (rig.trauma - rig.shake_decay * dt).max(0.);
+
+        // Squaring makes small amounts of trauma barely noticeable and large ones violent.
+        let shake = rig.trauma * rig.trauma;
+        let offset = Vec2::new(noise(rig.shake_time, 0.), noise(rig.shake_time, 1.)) * rig.shake_offset * shake;
+        let angle = noise(rig.shake_time, 2.) * rig.shake_angle * shake;
+
+        transform.translation = (rig.center + offset).extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(rig.angle + angle)
+    }
+}
+
+/// Fraction to move towards a target this frame at `rate`, independent of frame rate.
+fn smoothing(rate: f32, dt: f32) -> f32 {
+    1. - (-rate * dt).exp()
+}
+
+/// Wraps `angle` into `[-PI, PI)`, so rotating by it takes the short way around.
+fn wrap_angle(angle: f32) -> f32 {
+    (angle + PI).rem_euclid(TAU) - PI
+}
+
+fn clamp_within(center: Vec2, bounds: Rect, extents: Vec2) -> Vec2 {
+    let axis = |center: f32, min: f32, max: f32, extent: f32| {
+        if max - min <= extent * 2. { (min + max) / 2. } else { center.clamp(min + extent, max - extent) }
+    };
+
+    Vec2::new(
+        axis(center.x, bounds.min.x, bounds.max.x, extents.x),
+        axis(center.y, bounds.min.y, bounds.max.y, extents.y),
+    )
+}
+
+/// Smooth pseudo-random wobble in `[-1, 1]`, decorrelated by `seed`.
+fn noise(time: f32, seed: f32) -> f32 {
+    let t = time * 23. + seed * 17.;
+    ((t.sin() + (t * 2.3 + 1.7).sin() * 0.5 + (t * 4.1 + 0.3).sin() * 0.25) / 1.75).clamp(-1., 1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    fn app() -> (App, Entity, Entity) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TransformPlugin, CameraPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(16)));
+
+        let camera = app
+            .world_mut()
+            .spawn((CameraRig::default(), Transform::default(), OrthographicProjection {
+                area: Rect::new(-100., -50., 100., 50.),
+                ..OrthographicProjection::default_2d()
+            }))
+            .id();
+        let target = app.world_mut().spawn((CameraTarget, Transform::default())).id();
+
+        (app, camera, target)
+    }
+
+    fn rig(app: &App, camera: Entity) -> CameraRig {
+        *app.world().get::<CameraRig>(camera).unwrap()
+    }
+
+    fn move_target(app: &mut App, target: Entity, to: Vec2) {
+        app.world_mut().get_mut::<Transform>(target).unwrap().translation = to.extend(0.)
+    }
+
+    #[test]
+    fn follows_past_dead_zone() {
+        let (mut app, camera, target) = app();
+        app.update();
+        app.update();
+        assert_eq!(rig(&app, camera).center(), Vec2::ZERO);
+
+        move_target(&mut app, target, Vec2::new(40., 0.));
+        for _ in 0..60 {
+            app.update()
+        }
+        assert_eq!(rig(&app, camera).center(), Vec2::ZERO, "still within the dead zone");
+
+        move_target(&mut app, target, Vec2::new(148., 0.));
+        for _ in 0..120 {
+            app.update()
+        }
+        assert!(rig(&app, camera).center().distance(Vec2::new(100., 0.)) < 0.1);
+
+        app.world_mut()
+            .entity_mut(camera)
+            .insert(CameraBounds(Rect::new(-200., -200., 150., 200.)));
+        app.update();
+        assert_eq!(rig(&app, camera).center().x, 50.);
+    }
+
+    #[test]
+    fn rotates_to_local_up() {
+        let (mut app, camera, target) = app();
+        app.world_mut().entity_mut(target).insert(LocalGravity::default());
+        app.update();
+        app.update();
+        assert_eq!(rig(&app, camera).angle(), 0.);
+
+        app.world_mut().entity_mut(target).insert(LocalGravity::new(Vec2::NEG_X));
+        for _ in 0..120 {
+            app.update()
+        }
+
+        // Down the screen is now -X, so the camera's up is +X.
+        let rotation = app.world().get::<Transform>(camera).unwrap().rotation;
+        assert!((rotation * Vec3::Y).distance(Vec3::X) < 1e-3);
+    }
+
+    #[test]
+    fn shake_wears_off() {
+        let (mut app, camera, _) = app();
+        app.update();
+        app.world_mut().send_event(CameraShake(0.5));
+        app.world_mut().send_event(CameraShake(0.8));
+        app.update();
+
+        let trauma = rig(&app, camera).trauma();
+        assert!(trauma > 0.9 && trauma <= 1.);
+        assert_ne!(app.world().get::<Transform>(camera).unwrap().translation, Vec3::ZERO);
+
+        for _ in 0..60 {
+            app.update()
+        }
+        assert_eq!(rig(&app, camera).trauma(), 0.);
+        assert_eq!(app.world().get::<Transform>(camera).unwrap().translation, Vec3::ZERO);
+    }
+}
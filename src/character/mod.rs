@@ -0,0 +1,437 @@
+use std::time::Duration;
+
+use avian2d::prelude::*;
+use bevy::{ecs::query::QueryData, prelude::*};
+use leafwing_input_manager::prelude::*;
+
+use crate::{CoyoteTime, Dash, GravitySystem, InputBuffer, Jump, LocalGravity, control::Move};
+
+/// A physics-driven body steered by its [`Move`], [`Jump`], and [`Dash`] actions, as tuned
+/// by its [`CharacterMotion`]. It stands and moves relative to its [`LocalGravity`]. The actions
+/// are routed from a [`Player`](crate::Player)'s input, or can be set directly, e.g. by AI.
+#[derive(Component, Copy, Clone, Default, Debug)]
+#[require(
+    RigidBody(|| RigidBody::Dynamic),
+    Collider(Self::collider),
+    LockedAxes(|| LockedAxes::ROTATION_LOCKED),
+    Friction(|| Friction::ZERO.with_combine_rule(CoefficientCombine::Min)),
+    ShapeCaster(Self::ground_caster),
+    LocalGravity,
+    CharacterMotion,
+    CharacterState,
+    ActionState<Move>,
+    ActionState<Jump>,
+    ActionState<Dash>,
+    InputBuffer<Jump>,
+    InputBuffer<Dash>,
+    CoyoteTime
+)]
+pub struct Character;
+impl Character {
+    pub const RADIUS: f32 = 12.;
+    pub const LENGTH: f32 = 24.;
+
+    fn collider() -> Collider {
+        Collider::capsule(Self::RADIUS, Self::LENGTH)
+    }
+
+    fn ground_caster() -> ShapeCaster {
+        // Slightly narrower than the collider, so walls touching its sides don't count as ground.
+        ShapeCaster::new(
+            Collider::capsule(Self::RADIUS * 0.9, Self::LENGTH),
+            Vec2::ZERO,
+            0.,
+            Dir2::NEG_Y,
+        )
+        .with_max_distance(2.)
+    }
+}
+
+/// Tunables of a [`Character`]'s movement, in world units and seconds.
+#[derive(Component, Copy, Clone, Debug)]
+pub struct CharacterMotion {
+    pub max_speed: f32,
+    /// Horizontal acceleration towards [`max_speed`](Self::max_speed) while moving.
+    pub acceleration: f32,
+    /// Horizontal deceleration while not moving or turning around.
+    pub deceleration: f32,
+    /// Multiplier to acceleration and deceleration while airborne.
+    pub air_control: f32,
+    /// Steepest slope, in radians, that still counts as ground.
+    pub max_slope: f32,
+    pub jump_speed: f32,
+    /// Multiplier to upward velocity when jump is released early, so short presses jump lower.
+    pub jump_cut: f32,
+    pub dash_speed: f32,
+    pub dash_duration: Duration,
+    /// Time from the start of a dash until the next one.
+    pub dash_cooldown: Duration,
+    /// Time from the start of a dash during which the character can't be hurt.
+    pub dash_invulnerability: Duration,
+}
+
+impl Default for CharacterMotion {
+    fn default() -> Self {
+        Self {
+            max_speed: 240.,
+            acceleration: 2400.,
+            deceleration: 3200.,
+            air_control: 0.6,
+            max_slope: 50f32.to_radians(),
+            jump_speed: 520.,
+            jump_cut: 0.5,
+            dash_speed: 720.,
+            dash_duration: Duration::from_millis(150),
+            dash_cooldown: Duration::from_millis(600),
+            dash_invulnerability: Duration::from_millis(200),
+        }
+    }
+}
+
+#[derive(Component, Copy, Clone, Debug)]
+pub struct CharacterState {
+    grounded: bool,
+    /// Either -1 or 1, used to dash without a direction.
+    facing: f32,
+    /// Whether the current jump can still be cut short.
+    jumping: bool,
+    dash: Option<(Vec2, Duration)>,
+    dash_cooldown: Duration,
+    invulnerable: Duration,
+}
+
+impl CharacterState {
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    pub fn facing(&self) -> f32 {
+        self.facing
+    }
+
+    pub fn is_dashing(&self) -> bool {
+        self.dash.is_some()
+    }
+
+    pub fn can_dash(&self) -> bool {
+        self.dash_cooldown.is_zero()
+    }
+
+    pub fn is_invulnerable(&self) -> bool {
+        !self.invulnerable.is_zero()
+    }
+}
+
+impl Default for CharacterState {
+    fn default() -> Self {
+        Self {
+            grounded: false,
+            facing: 1.,
+            jumping: false,
+            dash: None,
+            dash_cooldown: Duration::ZERO,
+            invulnerable: Duration::ZERO,
+        }
+    }
+}
+
+#[derive(SystemSet, Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum CharacterSystem {
+    DetectGround,
+    Move,
+}
+
+pub struct CharacterPlugin;
+impl Plugin for CharacterPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_sets(
+            FixedUpdate,
+            (CharacterSystem::DetectGround, CharacterSystem::Move)
+                .chain()
+                .after(GravitySystem::Apply),
+        )
+        .add_systems(
+            FixedUpdate,
+            (
+                detect_ground.in_set(CharacterSystem::DetectGround),
+                move_characters.in_set(CharacterSystem::Move),
+            ),
+        );
+    }
+}
+
+type GroundQuery<'a> = (
+    &'a CharacterMotion,
+    &'a LocalGravity,
+    &'a ShapeHits,
+    &'a mut CharacterState,
+    Option<&'a mut CoyoteTime>,
+);
+
+fn detect_ground(mut query: Query<GroundQuery>) {
+    for (motion, gravity, hits, mut state, coyote) in &mut query {
+        let min_dot = motion.max_slope.cos();
+        state.grounded = hits.iter().any(|hit| hit.normal1.dot(*gravity.up()) >= min_dot);
+
+        if let Some(mut coyote) = coyote {
+            coyote.set_grounded(state.grounded)
+        }
+    }
+}
+
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct CharacterQuery {
+    motion: &'static CharacterMotion,
+    state: &'static mut CharacterState,
+    gravity: &'static LocalGravity,
+    rotation: &'static mut Rotation,
+    velocity: &'static mut LinearVelocity,
+    movement: &'static ActionState<Move>,
+    jump: &'static ActionState<Jump>,
+    jump_buffer: &'static mut InputBuffer<Jump>,
+    dash_buffer: &'static mut InputBuffer<Dash>,
+    coyote: &'static mut CoyoteTime,
+}
+
+fn move_characters(time: Res<Time>, mut query: Query<CharacterQuery, With<Character>>) {
+    let delta = time.delta();
+    let dt = time.delta_secs();
+
+    for CharacterQueryItem {
+        motion,
+        mut state,
+        gravity,
+        mut rotation,
+        mut velocity,
+        movement,
+        jump,
+        mut jump_buffer,
+        mut dash_buffer,
+        mut coyote,
+    } in &mut query
+    {
+        state.dash_cooldown = state.dash_cooldown.saturating_sub(delta);
+        state.invulnerable = state.invulnerable.saturating_sub(delta);
+
+        // Stand upright on whatever the character is falling towards, and move relative to that.
+        let (up, right) = (*gravity.up(), *gravity.right());
+        *rotation = Rotation::radians(right.to_angle());
+
+        let axis = movement.axis_pair(&Move);
+        if axis.x != 0. {
+            state.facing = axis.x.signum()
+        }
+
+        if let Some((dir, remaining)) = state.dash {
+            let remaining = remaining.saturating_sub(delta);
+            if remaining.is_zero() {
+                // Keep the dash's momentum, but no more than regular movement would give.
+                velocity.0 = dir * motion.max_speed;
+                state.dash = None
+            } else {
+                velocity.0 = dir * motion.dash_speed;
+                state.dash = Some((dir, remaining));
+                continue
+            }
+        }
+
+        // A press during cooldown stays buffered, so it dashes as soon as the cooldown ends.
+        if state.can_dash() && dash_buffer.consume() {
+            let dir = (right * axis.x + up * axis.y).try_normalize().unwrap_or(right * state.facing);
+            velocity.0 = dir * motion.dash_speed;
+            state.dash = Some((dir, motion.dash_duration));
+            state.dash_cooldown = motion.dash_cooldown;
+            state.invulnerable = motion.dash_invulnerability;
+            state.jumping = false;
+            continue
+        }
+
+        let (mut lateral, mut vertical) = (velocity.dot(right), velocity.dot(up));
+        let target = axis.x * motion.max_speed;
+        let accelerating = target != 0. && (lateral == 0. || target.signum() == lateral.signum());
+        let mut rate = if accelerating { motion.acceleration } else { motion.deceleration };
+        if !state.grounded {
+            rate *= motion.air_control
+        }
+
+        lateral = move_towards(lateral, target, rate * dt);
+
+        if coyote.try_jump(&mut jump_buffer) {
+            vertical = motion.jump_speed;
+            state.jumping = true
+        } else if state.jumping && (!jump.pressed(&Jump) || vertical <= 0.) {
+            if vertical > 0. {
+                vertical *= motion.jump_cut
+            }
+
+            state.jumping = false
+        }
+
+        velocity.0 = right * lateral + up * vertical
+    }
+}
+
+fn move_towards(from: f32, to: f32, max_delta: f32) -> f32 {
+    from + (to - from).clamp(-max_delta, max_delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{input::InputPlugin, time::TimeUpdateStrategy};
+
+    use super::*;
+    use crate::{ControlPlugins, GravityPlugin, Player, planetoid};
+
+    const STEP: Duration = Duration::from_micros(15625);
+
+    fn app() -> (App, Entity) {
+        app_on(
+            (RigidBody::Static, Collider::rectangle(4000., 20.)),
+            Vec2::new(0., -10.),
+            Vec2::new(0., 40.),
+        )
+    }
+
+    fn app_on(ground: impl Bundle, at: Vec2, spawn: Vec2) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            InputPlugin,
+            PhysicsPlugins::default(),
+            ControlPlugins,
+            GravityPlugin,
+            CharacterPlugin,
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(STEP))
+        .insert_resource(Gravity(Vec2::NEG_Y * 1400.));
+
+        app.world_mut().spawn((ground, Transform::from_translation(at.extend(0.))));
+        let player = app
+            .world_mut()
+            .spawn((Player(0), Character, Transform::from_translation(spawn.extend(0.))))
+            .id();
+
+        (app, player)
+    }
+
+    fn run(app: &mut App, frames: usize) {
+        for _ in 0..frames {
+            app.update()
+        }
+    }
+
+    fn keys(app: &mut App) -> Mut<'_, ButtonInput<KeyCode>> {
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>()
+    }
+
+    fn state(app: &App, player: Entity) -> CharacterState {
+        *app.world().get::<CharacterState>(player).unwrap()
+    }
+
+    fn position(app: &App, player: Entity) -> Vec2 {
+        app.world().get::<Transform>(player).unwrap().translation.truncate()
+    }
+
+    #[test]
+    fn lands_runs_and_stops() {
+        let (mut app, player) = app();
+        run(&mut app, 60);
+        assert!(state(&app, player).is_grounded());
+
+        keys(&mut app).press(KeyCode::KeyD);
+        run(&mut app, 30);
+        let velocity = app.world().get::<LinearVelocity>(player).unwrap().x;
+        assert!((velocity - CharacterMotion::default().max_speed).abs() < 1., "{velocity}");
+
+        keys(&mut app).release(KeyCode::KeyD);
+        run(&mut app, 30);
+        let velocity = app.world().get::<LinearVelocity>(player).unwrap().x;
+        assert!(velocity.abs() < 1., "{velocity}");
+    }
+
+    #[test]
+    fn variable_jump_height() {
+        let peak = |hold: usize| {
+            let (mut app, player) = app();
+            run(&mut app, 60);
+
+            keys(&mut app).press(KeyCode::Space);
+            let mut peak = f32::MIN;
+            for frame in 0..90 {
+                if frame == hold {
+                    keys(&mut app).release(KeyCode::Space)
+                }
+
+                app.update();
+                peak = peak.max(position(&app, player).y)
+            }
+
+            assert!(state(&app, player).is_grounded());
+            peak
+        };
+
+        let (short, long) = (peak(3), peak(60));
+        assert!(short + 20. < long, "short hop peaked at {short}, long jump at {long}");
+    }
+
+    #[test]
+    fn dash_cooldown_and_iframes() {
+        let (mut app, player) = app();
+        run(&mut app, 60);
+
+        keys(&mut app).press(KeyCode::ShiftLeft);
+        app.update();
+        let dashing = state(&app, player);
+        assert!(dashing.is_dashing() && dashing.is_invulnerable() && !dashing.can_dash());
+        keys(&mut app).release(KeyCode::ShiftLeft);
+
+        run(&mut app, 20);
+        let after = state(&app, player);
+        assert!(!after.is_dashing() && !after.is_invulnerable() && !after.can_dash());
+        assert!(position(&app, player).x > 50.);
+
+        run(&mut app, 30);
+        assert!(state(&app, player).can_dash());
+    }
+
+    #[test]
+    fn moves_without_player() {
+        let (mut app, ..) = app();
+        let character = app.world_mut().spawn((Character, Transform::from_xyz(200., 40., 0.))).id();
+        run(&mut app, 60);
+        assert!(state(&app, character).is_grounded());
+
+        // Steer it from the fixed loop the way an AI would, with no input map involved.
+        app.add_systems(FixedPreUpdate, |mut query: Query<&mut ActionState<Move>, Without<Player>>| {
+            for mut movement in &mut query {
+                movement.set_axis_pair(&Move, Vec2::X)
+            }
+        });
+        run(&mut app, 30);
+        let velocity = app.world().get::<LinearVelocity>(character).unwrap().x;
+        assert!((velocity - CharacterMotion::default().max_speed).abs() < 1., "{velocity}");
+    }
+
+    #[test]
+    fn walks_around_planetoid() {
+        let (mut app, player) = app_on(planetoid(200., 1400., 4.), Vec2::ZERO, Vec2::new(0., 260.));
+        run(&mut app, 60);
+        assert!(state(&app, player).is_grounded());
+
+        keys(&mut app).press(KeyCode::KeyD);
+        run(&mut app, 120);
+
+        // Walking right from the top goes clockwise, staying on the surface and upright relative to it.
+        let at = position(&app, player);
+        let surface = 200. + Character::RADIUS + Character::LENGTH / 2.;
+        assert!(at.x > 100. && (at.length() - surface).abs() < 2., "{at}");
+        assert!(state(&app, player).is_grounded());
+
+        let up = *app.world().get::<LocalGravity>(player).unwrap().up();
+        let rotation = *app.world().get::<Rotation>(player).unwrap();
+        assert!((rotation * Vec2::Y).dot(up) > 0.999);
+        assert!(up.dot(at.normalize()) > 0.999);
+    }
+}
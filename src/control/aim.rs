@@ -0,0 +1,81 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use leafwing_input_manager::prelude::*;
+
+use crate::{AssignedGamepad, Controller, PrimaryCamera};
+
+/// Aims players without an [`AssignedGamepad`] at the cursor, projected through the
+/// [`PrimaryCamera`]. Players with one aim with the stick instead, already read by the input map.
+pub fn aim_at_cursor(
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera: Query<(&Camera, &GlobalTransform), With<PrimaryCamera>>,
+    mut players: Query<(&GlobalTransform, &mut ActionState<Controller>), Without<AssignedGamepad>>,
+) {
+    let Ok(window) = window.get_single() else { return };
+    let Ok((camera, camera_transform)) = camera.get_single() else { return };
+    let Some(cursor) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+    else {
+        return
+    };
+
+    for (transform, mut state) in &mut players {
+        state.set_axis_pair(
+            &Controller::Aim,
+            (cursor - transform.translation().truncate()).normalize_or_zero(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        render::camera::{ManualTextureViews, camera_system},
+        window::{WindowCreated, WindowResized, WindowResolution, WindowScaleFactorChanged},
+    };
+
+    use super::*;
+    use crate::Player;
+
+    #[test]
+    fn aims_at_cursor_in_world() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TransformPlugin))
+            .init_resource::<Assets<Image>>()
+            .init_resource::<ManualTextureViews>()
+            .add_event::<WindowCreated>()
+            .add_event::<WindowResized>()
+            .add_event::<WindowScaleFactorChanged>()
+            .add_event::<AssetEvent<Image>>()
+            .add_systems(Update, (camera_system::<OrthographicProjection>, aim_at_cursor).chain());
+
+        // 200 logical pixels right of and above the window's center, with y pointing down.
+        let mut window = Window {
+            resolution: WindowResolution::new(800., 600.),
+            ..default()
+        };
+        window.set_cursor_position(Some(Vec2::new(600., 100.)));
+        app.world_mut().spawn((window, PrimaryWindow));
+
+        app.world_mut().spawn((PrimaryCamera, Transform::from_xyz(100., 50., 0.)));
+        let player = app.world_mut().spawn((Player(0), Transform::from_xyz(100., 50., 0.))).id();
+        let gamepad = app.world_mut().spawn_empty().id();
+        let other = app
+            .world_mut()
+            .spawn((Player(1), AssignedGamepad(gamepad), Transform::default()))
+            .id();
+
+        // Global transforms are only propagated at the end of the first update.
+        app.update();
+        app.update();
+
+        let aim = |player| {
+            app.world()
+                .get::<ActionState<Controller>>(player)
+                .unwrap()
+                .axis_pair(&Controller::Aim)
+        };
+        assert!(aim(player).abs_diff_eq(Vec2::ONE.normalize(), 1e-5), "{}", aim(player));
+        assert_eq!(aim(other), Vec2::ZERO, "players with a gamepad aim with its stick");
+    }
+}
@@ -0,0 +1,190 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use crate::{Dash, Jump, RouteSplitActions};
+
+/// Remembers a press of split action `A` for [`window`](Self::window), so a press slightly before
+/// it can take effect (e.g. jumping just before landing) isn't lost. Gameplay takes the press with
+/// [`consume`](Self::consume).
+#[derive(Component, Copy, Clone, Debug)]
+pub struct InputBuffer<A: Actionlike> {
+    pub action: A,
+    pub window: Duration,
+    /// Time since the buffered press, if any.
+    age: Option<Duration>,
+}
+
+impl<A: Actionlike> InputBuffer<A> {
+    pub const DEFAULT_WINDOW: Duration = Duration::from_millis(120);
+
+    pub const fn new(action: A, window: Duration) -> Self {
+        Self {
+            action,
+            window,
+            age: None,
+        }
+    }
+
+    pub fn is_buffered(&self) -> bool {
+        self.age.is_some()
+    }
+
+    /// Takes the buffered press, returning whether there was one.
+    pub fn consume(&mut self) -> bool {
+        self.age.take().is_some()
+    }
+
+    pub fn clear(&mut self) {
+        self.age = None
+    }
+
+    fn tick(&mut self, delta: Duration, just_pressed: bool) {
+        self.age = if just_pressed {
+            Some(Duration::ZERO)
+        } else {
+            self.age.map(|age| age + delta).filter(|&age| age <= self.window)
+        }
+    }
+}
+
+impl<A: Actionlike + Default> Default for InputBuffer<A> {
+    fn default() -> Self {
+        Self::new(default(), Self::DEFAULT_WINDOW)
+    }
+}
+
+/// Keeps jumps available for [`window`](Self::window) after leaving the ground, so a jump pressed
+/// just after running off a ledge still happens. Gameplay reports contact with
+/// [`set_grounded`](Self::set_grounded).
+#[derive(Component, Copy, Clone, Debug)]
+pub struct CoyoteTime {
+    pub window: Duration,
+    grounded: bool,
+    airborne: Duration,
+    used: bool,
+}
+
+impl CoyoteTime {
+    pub const DEFAULT_WINDOW: Duration = Duration::from_millis(100);
+
+    pub const fn new(window: Duration) -> Self {
+        Self {
+            window,
+            grounded: false,
+            airborne: Duration::ZERO,
+            used: true,
+        }
+    }
+
+    pub fn set_grounded(&mut self, grounded: bool) {
+        if grounded {
+            self.airborne = Duration::ZERO;
+            self.used = false
+        }
+
+        self.grounded = grounded
+    }
+
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    /// Whether a jump is allowed now: on the ground, or airborne within the window without having
+    /// jumped.
+    pub fn can_jump(&self) -> bool {
+        !self.used && (self.grounded || self.airborne <= self.window)
+    }
+
+    /// Jumps if both allowed and buffered in `buffer`, consuming both.
+    pub fn try_jump<A: Actionlike>(&mut self, buffer: &mut InputBuffer<A>) -> bool {
+        if self.can_jump() && buffer.consume() {
+            self.used = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn tick(&mut self, delta: Duration) {
+        if !self.grounded {
+            self.airborne += delta
+        }
+    }
+}
+
+impl Default for CoyoteTime {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WINDOW)
+    }
+}
+
+pub struct InputBufferPlugin;
+impl Plugin for InputBufferPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            (tick_input_buffers::<Jump>, tick_input_buffers::<Dash>, tick_coyote_time).after(RouteSplitActions),
+        );
+    }
+}
+
+fn tick_input_buffers<A: Actionlike>(time: Res<Time>, mut query: Query<(&ActionState<A>, &mut InputBuffer<A>)>) {
+    for (state, mut buffer) in &mut query {
+        let just_pressed = state.just_pressed(&buffer.action);
+        buffer.tick(time.delta(), just_pressed)
+    }
+}
+
+fn tick_coyote_time(time: Res<Time>, mut query: Query<&mut CoyoteTime>) {
+    for mut coyote in &mut query {
+        coyote.tick(time.delta())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: Duration = Duration::from_millis(50);
+
+    #[test]
+    fn buffer_expires() {
+        let mut buffer = InputBuffer::<Jump>::default();
+        buffer.tick(FRAME, true);
+        buffer.tick(FRAME, false);
+        buffer.tick(FRAME, false);
+        assert!(buffer.consume());
+        assert!(!buffer.consume());
+
+        buffer.tick(FRAME, true);
+        for _ in 0..3 {
+            buffer.tick(FRAME, false)
+        }
+        assert!(!buffer.is_buffered());
+    }
+
+    #[test]
+    fn coyote_jump() {
+        let mut coyote = CoyoteTime::default();
+        let mut buffer = InputBuffer::<Jump>::default();
+        buffer.tick(FRAME, true);
+        assert!(!coyote.try_jump(&mut buffer), "can't jump before ever landing");
+
+        coyote.set_grounded(true);
+        coyote.set_grounded(false);
+        coyote.tick(FRAME);
+        assert!(coyote.try_jump(&mut buffer));
+
+        // Jumping spends the coyote time, and the press was consumed.
+        buffer.tick(FRAME, true);
+        assert!(!coyote.try_jump(&mut buffer));
+        assert!(buffer.is_buffered());
+
+        coyote.set_grounded(true);
+        coyote.set_grounded(false);
+        coyote.tick(FRAME * 3);
+        assert!(!coyote.try_jump(&mut buffer), "coyote time ran out");
+    }
+}
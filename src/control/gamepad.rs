@@ -0,0 +1,215 @@
+use bevy::{input::gamepad::Gamepad, prelude::*};
+use leafwing_input_manager::prelude::*;
+
+use crate::{Controller, InputGamepadPref, InputKeyboardPref, Player, Stick};
+
+/// The gamepad driving a [`Player`]. Connected gamepads are handed out to players without one, and
+/// taken back once disconnected.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug)]
+pub struct AssignedGamepad(pub Entity);
+
+impl Stick {
+    pub const fn gamepad_stick(self) -> GamepadStick {
+        match self {
+            Self::Left => GamepadStick::LEFT,
+            Self::Right => GamepadStick::RIGHT,
+        }
+    }
+
+    pub const fn other(self) -> Self {
+        match self {
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+        }
+    }
+}
+
+impl InputGamepadPref {
+    pub fn insert_into(&self, map: &mut InputMap<Controller>) {
+        map.insert(Controller::Primary, self.primary)
+            .insert(Controller::Secondary, self.secondary)
+            .insert(Controller::Jump, self.jump)
+            .insert(Controller::Dash, self.dash)
+            .insert_dual_axis(Controller::Move, self.movement.gamepad_stick())
+            .insert_dual_axis(Controller::Aim, self.movement.other().gamepad_stick());
+    }
+}
+
+/// Builds a player's full input map. Without an assigned gamepad, gamepad bindings listen to no
+/// gamepad at all rather than to any of them, so unassigned players in local co-op don't steal each
+/// other's input.
+pub fn player_input_map(
+    keyboard: &InputKeyboardPref,
+    gamepad: &InputGamepadPref,
+    assigned: Option<&AssignedGamepad>,
+) -> InputMap<Controller> {
+    let mut map = keyboard.input_map();
+    gamepad.insert_into(&mut map);
+    map.set_gamepad(assigned.map_or(Entity::PLACEHOLDER, |&AssignedGamepad(gamepad)| gamepad));
+    map
+}
+
+pub(crate) fn assign_gamepads(
+    mut commands: Commands,
+    gamepads: Query<Entity, With<Gamepad>>,
+    players: Query<(Entity, &Player, Option<&AssignedGamepad>)>,
+) {
+    let mut vacant = Vec::new();
+    let mut taken = Vec::new();
+    for (e, &player, assigned) in &players {
+        match assigned {
+            Some(&AssignedGamepad(gamepad)) if gamepads.contains(gamepad) => taken.push(gamepad),
+            Some(..) => {
+                commands.entity(e).remove::<AssignedGamepad>();
+                vacant.push((player, e))
+            }
+            None => vacant.push((player, e)),
+        }
+    }
+
+    // Hand gamepads out by player number, so player 0 gets the first connected gamepad.
+    let mut free = gamepads.iter().filter(|gamepad| !taken.contains(gamepad)).collect::<Vec<_>>();
+    vacant.sort_unstable();
+    free.sort_unstable();
+
+    for ((.., player), gamepad) in vacant.into_iter().zip(free) {
+        commands.entity(player).insert(AssignedGamepad(gamepad));
+    }
+}
+
+type PlayerInput<'a> = (
+    Ref<'a, Player>,
+    Option<Ref<'a, AssignedGamepad>>,
+    &'a mut InputMap<Controller>,
+);
+
+pub(crate) fn apply_input_prefs(
+    keyboard: Res<InputKeyboardPref>,
+    gamepad: Res<InputGamepadPref>,
+    mut unassigned: RemovedComponents<AssignedGamepad>,
+    mut players: Query<PlayerInput>,
+) {
+    let prefs_changed = keyboard.is_changed() || gamepad.is_changed();
+    for (player, assigned, mut map) in &mut players {
+        if prefs_changed || player.is_added() || assigned.as_ref().is_some_and(Ref::is_changed) {
+            *map = player_input_map(&keyboard, &gamepad, assigned.as_deref())
+        }
+    }
+
+    for e in unassigned.read() {
+        if let Ok((.., None, mut map)) = players.get_mut(e) {
+            *map = player_input_map(&keyboard, &gamepad, None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::{
+        InputPlugin,
+        gamepad::{GamepadConnection, GamepadConnectionEvent, gamepad_connection_system},
+    };
+
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin, InputManagerPlugin::<Controller>::default()))
+            .init_resource::<InputKeyboardPref>()
+            .init_resource::<InputGamepadPref>()
+            .add_systems(
+                PreUpdate,
+                (assign_gamepads, apply_input_prefs)
+                    .chain()
+                    .after(gamepad_connection_system)
+                    .before(leafwing_input_manager::plugin::InputManagerSystem::Update),
+            );
+        app
+    }
+
+    fn connect(app: &mut App) -> Entity {
+        let gamepad = app.world_mut().spawn_empty().id();
+        app.world_mut()
+            .send_event(GamepadConnectionEvent::new(gamepad, GamepadConnection::Connected {
+                name: "Synthetic".into(),
+                vendor_id: None,
+                product_id: None,
+            }));
+
+        app.update();
+        gamepad
+    }
+
+    fn assigned(app: &App, player: Entity) -> Option<Entity> {
+        app.world()
+            .get::<AssignedGamepad>(player)
+            .map(|&AssignedGamepad(gamepad)| gamepad)
+    }
+
+    #[test]
+    fn assigns_per_player() {
+        let mut app = app();
+        // Spawned out of order, which gamepads don't go by.
+        let second = app.world_mut().spawn(Player(1)).id();
+        let first = app.world_mut().spawn(Player(0)).id();
+        app.update();
+
+        let pad_a = connect(&mut app);
+        let pad_b = connect(&mut app);
+        assert_eq!(assigned(&app, first), Some(pad_a));
+        assert_eq!(assigned(&app, second), Some(pad_b));
+        assert_eq!(
+            app.world().get::<InputMap<Controller>>(second).unwrap().gamepad(),
+            Some(pad_b)
+        );
+
+        // A third gamepad has no one left to drive, until a player's gamepad disconnects.
+        let pad_c = connect(&mut app);
+        assert_eq!(assigned(&app, first), Some(pad_a));
+
+        for (pad, expected) in [(pad_a, Some(pad_c)), (pad_c, None)] {
+            app.world_mut()
+                .send_event(GamepadConnectionEvent::new(pad, GamepadConnection::Disconnected));
+            app.update();
+            assert_eq!(assigned(&app, first), expected);
+        }
+
+        assert_eq!(
+            app.world().get::<InputMap<Controller>>(first).unwrap().gamepad(),
+            Some(Entity::PLACEHOLDER)
+        );
+    }
+
+    #[test]
+    fn reads_assigned_gamepad() {
+        let mut app = app();
+        let player = app.world_mut().spawn(Player(0)).id();
+        let other = app.world_mut().spawn(Player(1)).id();
+        app.update();
+
+        let pad = connect(&mut app);
+        let mut pad_entity = app.world_mut().entity_mut(pad);
+        let mut pad = pad_entity.get_mut::<Gamepad>().unwrap();
+        pad.digital_mut().press(GamepadButton::South);
+        pad.analog_mut().set(GamepadAxis::RightStickX, 1.0);
+        app.update();
+
+        assert!(
+            app.world()
+                .get::<ActionState<Controller>>(player)
+                .unwrap()
+                .pressed(&Controller::Jump)
+        );
+        assert!(
+            !app.world()
+                .get::<ActionState<Controller>>(other)
+                .unwrap()
+                .pressed(&Controller::Jump)
+        );
+
+        // The stick not used for moving aims.
+        let state = app.world().get::<ActionState<Controller>>(player).unwrap();
+        assert_eq!(state.axis_pair(&Controller::Aim), Vec2::X);
+        assert_eq!(state.axis_pair(&Controller::Move), Vec2::ZERO);
+    }
+}
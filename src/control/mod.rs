@@ -0,0 +1,157 @@
+use bevy::{
+    app::{PluginGroupBuilder, RunFixedMainLoopSystem},
+    prelude::*,
+};
+use leafwing_input_manager::{plugin::InputManagerSystem, prelude::*, systems::update_action_state};
+
+use crate::{InputGamepadPref, InputKeyboardPref};
+
+mod aim;
+mod buffer;
+mod gamepad;
+mod rebind;
+mod replay;
+mod split;
+pub use aim::*;
+pub use buffer::*;
+pub use gamepad::*;
+pub use rebind::*;
+pub use replay::*;
+pub use split::*;
+
+#[derive(Actionlike, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Reflect)]
+#[reflect(Debug)]
+pub enum Attack {
+    Primary,
+    Secondary,
+}
+
+impl SplitAction<Controller> for Attack {
+    const ROUTES: &'static [(Controller, Self)] =
+        &[(Controller::Primary, Self::Primary), (Controller::Secondary, Self::Secondary)];
+}
+
+#[derive(Actionlike, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Reflect)]
+#[reflect(Debug)]
+pub struct Jump;
+
+impl SplitAction<Controller> for Jump {
+    const ROUTES: &'static [(Controller, Self)] = &[(Controller::Jump, Self)];
+}
+
+#[derive(Actionlike, Copy, Clone, Default, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Reflect)]
+#[reflect(Debug)]
+pub struct Dash;
+
+impl SplitAction<Controller> for Dash {
+    const ROUTES: &'static [(Controller, Self)] = &[(Controller::Dash, Self)];
+}
+
+#[derive(Actionlike, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Reflect)]
+#[actionlike(DualAxis)]
+#[reflect(Debug)]
+pub struct Move;
+
+impl SplitAction<Controller> for Move {
+    const ROUTES: &'static [(Controller, Self)] = &[(Controller::Move, Self)];
+}
+
+#[derive(Actionlike, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Reflect)]
+#[actionlike(DualAxis)]
+#[reflect(Debug)]
+pub struct Aim;
+
+impl SplitAction<Controller> for Aim {
+    const ROUTES: &'static [(Controller, Self)] = &[(Controller::Aim, Self)];
+}
+
+#[derive(Actionlike, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug, Reflect)]
+#[reflect(Debug)]
+pub enum Controller {
+    Primary,
+    Secondary,
+    Jump,
+    Dash,
+    #[actionlike(DualAxis)]
+    Move,
+    /// Unit direction attacks are aimed at, from the cursor or the stick not used for moving.
+    #[actionlike(DualAxis)]
+    Aim,
+}
+
+impl Controller {
+    pub const ALL: [Self; 6] = [
+        Self::Primary,
+        Self::Secondary,
+        Self::Jump,
+        Self::Dash,
+        Self::Move,
+        Self::Aim,
+    ];
+}
+
+impl MasterAction for Controller {
+    type Split = (Attack, Jump, Dash, Move, Aim);
+}
+
+/// A local player, numbered from 0. The number decides which gamepad it's handed first, and which
+/// recorded input drives it in a replay, so it has to stay the same while the player exists.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
+#[require(
+    ActionState<Controller>,
+    InputMap<Controller>(Self::default_map),
+    InputBuffer<Jump>,
+    InputBuffer<Dash>,
+    CoyoteTime
+)]
+pub struct Player(pub u8);
+impl Player {
+    fn default_map() -> InputMap<Controller> {
+        player_input_map(&default(), &default(), None)
+    }
+}
+
+impl InputKeyboardPref {
+    pub fn input_map(&self) -> InputMap<Controller> {
+        let [up, down, left, right] = self.movement;
+        InputMap::new([
+            (Controller::Primary, self.primary),
+            (Controller::Secondary, self.secondary),
+            (Controller::Jump, self.jump),
+            (Controller::Dash, self.dash),
+        ])
+        .with_dual_axis(Controller::Move, VirtualDPad::new(up, down, left, right))
+    }
+}
+
+pub struct ControlPlugins;
+impl PluginGroup for ControlPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(SplitActionPlugin::<Controller>::default())
+            .add(InputBufferPlugin)
+            .add(RebindPlugin)
+            .add(ReplayPlugin)
+            .add(|app: &mut App| {
+                app.add_systems(
+                    PreUpdate,
+                    (
+                        (assign_gamepads, apply_input_prefs)
+                            .chain()
+                            .run_if(resource_exists::<InputKeyboardPref>.and(resource_exists::<InputGamepadPref>))
+                            .before(InputManagerSystem::Update),
+                        aim_at_cursor
+                            .in_set(InputManagerSystem::ManualControl)
+                            .before(RouteSplitActions),
+                    ),
+                )
+                .add_systems(
+                    RunFixedMainLoop,
+                    aim_at_cursor
+                        .in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop)
+                        .after(update_action_state::<Controller>)
+                        .before(RouteSplitActions),
+                );
+            })
+    }
+}
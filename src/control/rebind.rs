@@ -0,0 +1,287 @@
+use bevy::prelude::*;
+
+use crate::InputKeyboardPref;
+
+/// A single rebindable key in [`InputKeyboardPref`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug, Reflect)]
+#[reflect(Debug)]
+pub enum KeyBinding {
+    Up,
+    Down,
+    Left,
+    Right,
+    Primary,
+    Secondary,
+    Jump,
+    Dash,
+}
+
+impl KeyBinding {
+    pub const ALL: [Self; 8] = [
+        Self::Up,
+        Self::Down,
+        Self::Left,
+        Self::Right,
+        Self::Primary,
+        Self::Secondary,
+        Self::Jump,
+        Self::Dash,
+    ];
+
+    pub fn get(self, pref: &InputKeyboardPref) -> KeyCode {
+        match self {
+            Self::Up => pref.movement[0],
+            Self::Down => pref.movement[1],
+            Self::Left => pref.movement[2],
+            Self::Right => pref.movement[3],
+            Self::Primary => pref.primary,
+            Self::Secondary => pref.secondary,
+            Self::Jump => pref.jump,
+            Self::Dash => pref.dash,
+        }
+    }
+
+    pub fn get_mut(self, pref: &mut InputKeyboardPref) -> &mut KeyCode {
+        match self {
+            Self::Up => &mut pref.movement[0],
+            Self::Down => &mut pref.movement[1],
+            Self::Left => &mut pref.movement[2],
+            Self::Right => &mut pref.movement[3],
+            Self::Primary => &mut pref.primary,
+            Self::Secondary => &mut pref.secondary,
+            Self::Jump => &mut pref.jump,
+            Self::Dash => &mut pref.dash,
+        }
+    }
+}
+
+/// Key that cancels listening instead of being bound.
+pub const CANCEL_KEY: KeyCode = KeyCode::Escape;
+
+#[derive(Event, Copy, Clone, Debug)]
+pub enum RebindRequest {
+    /// Binds the next pressed key to the given binding. Frames where several keys go down at once
+    /// are skipped, since there's no telling which one was meant.
+    Listen(KeyBinding),
+    /// Stops listening without changing anything, like pressing [`CANCEL_KEY`].
+    Cancel,
+    RestoreDefault(KeyBinding),
+    RestoreAllDefaults,
+}
+
+#[derive(Event, Copy, Clone, Debug)]
+pub enum RebindEvent {
+    /// `binding` is now bound to `key`. If `key` was already bound to `swapped`, that binding takes
+    /// over `binding`'s previous key so no key drives two actions.
+    Bound {
+        binding: KeyBinding,
+        key: KeyCode,
+        swapped: Option<KeyBinding>,
+    },
+    Cancelled(KeyBinding),
+    RestoredDefaults,
+}
+
+/// Which binding, if any, is waiting for the next key press.
+#[derive(Resource, Copy, Clone, Default, Debug)]
+pub struct Rebinding {
+    listening: Option<KeyBinding>,
+    /// Whether listening started this frame, so the key press that asked to listen, e.g. confirming
+    /// a menu entry, isn't captured as the new binding.
+    started: bool,
+}
+
+impl Rebinding {
+    pub fn listening(&self) -> Option<KeyBinding> {
+        self.listening
+    }
+}
+
+pub struct RebindPlugin;
+impl Plugin for RebindPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_event::<RebindRequest>()
+            .add_event::<RebindEvent>()
+            .add_systems(
+                Update,
+                (handle_rebind_requests, capture_rebind)
+                    .chain()
+                    .run_if(resource_exists::<InputKeyboardPref>),
+            );
+    }
+}
+
+fn handle_rebind_requests(
+    mut pref: ResMut<InputKeyboardPref>,
+    mut rebinding: ResMut<Rebinding>,
+    mut requests: EventReader<RebindRequest>,
+    mut events: EventWriter<RebindEvent>,
+) {
+    for &request in requests.read() {
+        match request {
+            RebindRequest::Listen(binding) => {
+                if let Some(previous) = rebinding.listening.replace(binding) {
+                    events.send(RebindEvent::Cancelled(previous));
+                }
+
+                rebinding.started = true
+            }
+            RebindRequest::Cancel => {
+                if let Some(binding) = rebinding.listening.take() {
+                    events.send(RebindEvent::Cancelled(binding));
+                }
+            }
+            RebindRequest::RestoreDefault(binding) => {
+                let key = binding.get(&default());
+                events.send(bind(&mut pref, binding, key));
+            }
+            RebindRequest::RestoreAllDefaults => {
+                *pref = default();
+                events.send(RebindEvent::RestoredDefaults);
+            }
+        }
+    }
+}
+
+fn capture_rebind(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut pref: ResMut<InputKeyboardPref>,
+    mut rebinding: ResMut<Rebinding>,
+    mut events: EventWriter<RebindEvent>,
+) {
+    let Some(binding) = rebinding.listening else { return };
+    if std::mem::take(&mut rebinding.started) {
+        return
+    }
+
+    if keys.just_pressed(CANCEL_KEY) {
+        rebinding.listening = None;
+        events.send(RebindEvent::Cancelled(binding));
+        return
+    }
+
+    let mut pressed = keys.get_just_pressed();
+    let (Some(&key), None) = (pressed.next(), pressed.next()) else { return };
+
+    rebinding.listening = None;
+    events.send(bind(&mut pref, binding, key));
+}
+
+fn bind(pref: &mut InputKeyboardPref, binding: KeyBinding, key: KeyCode) -> RebindEvent {
+    let previous = binding.get(pref);
+    let swapped = KeyBinding::ALL
+        .into_iter()
+        .find(|&other| other != binding && other.get(pref) == key);
+
+    if let Some(other) = swapped {
+        *other.get_mut(pref) = previous;
+    }
+
+    *binding.get_mut(pref) = key;
+    RebindEvent::Bound { binding, key, swapped }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins(RebindPlugin)
+            .init_resource::<InputKeyboardPref>()
+            .init_resource::<ButtonInput<KeyCode>>();
+        app
+    }
+
+    fn press(app: &mut App, key: KeyCode) {
+        let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        keys.reset_all();
+        keys.press(key);
+        app.update();
+    }
+
+    fn events(app: &mut App) -> Vec<RebindEvent> {
+        app.world_mut().resource_mut::<Events<RebindEvent>>().drain().collect()
+    }
+
+    #[test]
+    fn captures_and_swaps() {
+        let mut app = app();
+        app.world_mut().send_event(RebindRequest::Listen(KeyBinding::Jump));
+        app.update();
+        assert_eq!(app.world().resource::<Rebinding>().listening(), Some(KeyBinding::Jump));
+
+        press(&mut app, KeyCode::KeyK);
+        assert_eq!(app.world().resource::<InputKeyboardPref>().jump, KeyCode::KeyK);
+        assert_eq!(app.world().resource::<Rebinding>().listening(), None);
+
+        // Binding Dash to Jump's key hands Dash's old key over to Jump.
+        app.world_mut().send_event(RebindRequest::Listen(KeyBinding::Dash));
+        app.update();
+        press(&mut app, KeyCode::KeyK);
+
+        let pref = *app.world().resource::<InputKeyboardPref>();
+        assert_eq!((pref.dash, pref.jump), (KeyCode::KeyK, KeyCode::ShiftLeft));
+        assert!(matches!(
+            events(&mut app).last(),
+            Some(RebindEvent::Bound {
+                binding: KeyBinding::Dash,
+                swapped: Some(KeyBinding::Jump),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn cancel_and_restore() {
+        let mut app = app();
+        app.world_mut().send_event(RebindRequest::Listen(KeyBinding::Up));
+        app.update();
+        press(&mut app, CANCEL_KEY);
+        assert_eq!(app.world().resource::<InputKeyboardPref>().movement[0], KeyCode::KeyW);
+        assert!(matches!(events(&mut app)[..], [RebindEvent::Cancelled(KeyBinding::Up)]));
+
+        app.world_mut().send_event(RebindRequest::Listen(KeyBinding::Up));
+        app.update();
+        press(&mut app, KeyCode::ArrowUp);
+        assert_eq!(app.world().resource::<InputKeyboardPref>().movement[0], KeyCode::ArrowUp);
+
+        app.world_mut().send_event(RebindRequest::RestoreDefault(KeyBinding::Up));
+        app.update();
+        assert_eq!(app.world().resource::<InputKeyboardPref>().movement[0], KeyCode::KeyW);
+    }
+
+    #[test]
+    fn ignores_simultaneous_keys() {
+        let mut app = app();
+        app.world_mut().send_event(RebindRequest::Listen(KeyBinding::Jump));
+        app.update();
+
+        let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        keys.press(KeyCode::KeyK);
+        keys.press(KeyCode::KeyL);
+        app.update();
+        assert_eq!(app.world().resource::<InputKeyboardPref>().jump, KeyCode::Space);
+        assert_eq!(app.world().resource::<Rebinding>().listening(), Some(KeyBinding::Jump));
+        assert!(events(&mut app).is_empty());
+
+        press(&mut app, KeyCode::KeyL);
+        assert_eq!(app.world().resource::<InputKeyboardPref>().jump, KeyCode::KeyL);
+    }
+
+    #[test]
+    fn ignores_key_pressed_with_listen() {
+        let mut app = app();
+
+        // The key that confirmed "rebind Jump" in a menu is still just pressed as listening starts.
+        app.world_mut().send_event(RebindRequest::Listen(KeyBinding::Jump));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.world().resource::<InputKeyboardPref>().jump, KeyCode::Space);
+        assert_eq!(app.world().resource::<Rebinding>().listening(), Some(KeyBinding::Jump));
+        assert!(events(&mut app).is_empty());
+
+        press(&mut app, KeyCode::KeyK);
+        assert_eq!(app.world().resource::<InputKeyboardPref>().jump, KeyCode::KeyK);
+    }
+}
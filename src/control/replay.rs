@@ -0,0 +1,480 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    pin::pin,
+    time::Duration,
+};
+
+use bevy::{app::RunFixedMainLoopSystem, prelude::*, time::TimeUpdateStrategy, utils::ConditionalSendFuture};
+use leafwing_input_manager::{
+    InputControlKind, buttonlike::ButtonState, plugin::InputManagerSystem, prelude::*, systems::update_action_state,
+};
+
+use crate::{
+    Controller, LocalStorage, Player, RouteSplitActions, Storage, StoragePath, aim_at_cursor, persist::Persist, r, w,
+};
+
+/// The state of a single [`Controller`] action in a frame.
+#[derive(Persist, Copy, Clone, PartialEq, Debug)]
+#[persist(auto)]
+pub enum RecordedInput {
+    #[persist(tag = 0)]
+    Button { state: ButtonState, value: f32 },
+    #[persist(tag = 1)]
+    Axis(f32),
+    #[persist(tag = 2)]
+    DualAxis(Vec2),
+    #[persist(tag = 3)]
+    TripleAxis(Vec3),
+}
+
+/// One player's [`ActionState<Controller>`] in a single frame, in [`Controller::ALL`] order.
+#[derive(Persist, Clone, PartialEq, Debug)]
+#[persist(auto)]
+pub struct ControllerFrame {
+    pub inputs: Vec<RecordedInput>,
+}
+
+impl ControllerFrame {
+    pub fn capture(state: &ActionState<Controller>) -> Self {
+        Self {
+            inputs: Controller::ALL
+                .into_iter()
+                .map(|action| match action.input_control_kind() {
+                    InputControlKind::Button => {
+                        let (state, value) = state
+                            .button_data(&action)
+                            .map_or((ButtonState::Released, 0.), |data| (data.state, data.value));
+                        RecordedInput::Button { state, value }
+                    }
+                    InputControlKind::Axis => RecordedInput::Axis(state.value(&action)),
+                    InputControlKind::DualAxis => RecordedInput::DualAxis(state.axis_pair(&action)),
+                    InputControlKind::TripleAxis => RecordedInput::TripleAxis(state.axis_triple(&action)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Overwrites `state` with this frame. Button states are written as-is rather than pressed or
+    /// released, so held buttons stay held instead of being re-pressed every frame.
+    pub fn apply(&self, state: &mut ActionState<Controller>) {
+        for (action, &input) in Controller::ALL.into_iter().zip(&self.inputs) {
+            match input {
+                RecordedInput::Button { state: button, value } => {
+                    let data = state.button_data_mut_or_default(&action);
+                    data.state = button;
+                    data.value = value
+                }
+                RecordedInput::Axis(value) => state.set_value(&action, value),
+                RecordedInput::DualAxis(pair) => state.set_axis_pair(&action, pair),
+                RecordedInput::TripleAxis(triple) => state.set_axis_triple(&action, triple),
+            }
+        }
+    }
+
+    fn matches_controller(&self) -> bool {
+        self.inputs.len() == Controller::ALL.len() &&
+            Controller::ALL.into_iter().zip(&self.inputs).all(|(action, input)| {
+                matches!(
+                    (action.input_control_kind(), input),
+                    (InputControlKind::Button, RecordedInput::Button { .. }) |
+                        (InputControlKind::Axis, RecordedInput::Axis(..)) |
+                        (InputControlKind::DualAxis, RecordedInput::DualAxis(..)) |
+                        (InputControlKind::TripleAxis, RecordedInput::TripleAxis(..))
+                )
+            })
+    }
+}
+
+/// Every [`Player`]'s input in a single frame, and how long that frame took.
+#[derive(Persist, Clone, PartialEq, Debug)]
+#[persist(auto)]
+pub struct RecordedFrame {
+    /// [`Time<Real>`] delta of the frame, which decides how many times
+    /// [`FixedMain`](bevy::app::FixedMain) ran in it.
+    pub delta: Duration,
+    /// Input of each player, by player number.
+    pub players: BTreeMap<u8, ControllerFrame>,
+}
+
+/// Recorded input of every [`Player`], frame by frame. Each player replays the input recorded for
+/// its own player number, so a replay only reproduces a session with the same players.
+///
+/// Replaying advances time by each recorded frame's delta, and requires the same [`Time<Fixed>`]
+/// timestep, so fixed-timestep gameplay sees the same input on the same ticks as when it was
+/// recorded.
+#[derive(Persist, Clone, Default, PartialEq, Debug)]
+#[persist(version = 0, auto)]
+pub struct InputRecording {
+    pub timestep: Duration,
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl LocalStorage {
+    /// Reads a recording, rejecting ones recorded with a different set of [`Controller`] actions.
+    pub fn read_replay(&self, name: &str) -> impl ConditionalSendFuture<Output = IoResult<InputRecording>> + use<> {
+        let reader = StoragePath::name(&format!("{name}.replay")).map(|file| self.reader(Storage::Replays, file));
+        async move {
+            let mut r = pin!(reader?.await?);
+            let recording = r!(r, InputRecording)?;
+
+            if !recording
+                .frames
+                .iter()
+                .flat_map(|frame| frame.players.values())
+                .all(ControllerFrame::matches_controller)
+            {
+                return Err(IoError::new(
+                    IoErrorKind::InvalidData,
+                    "Replay was recorded with different controller actions",
+                ))
+            }
+
+            Ok(recording)
+        }
+    }
+
+    pub fn write_replay(
+        &self,
+        name: &str,
+        recording: InputRecording,
+    ) -> impl ConditionalSendFuture<Output = IoResult<()>> + use<> {
+        let writer = StoragePath::name(&format!("{name}.replay")).map(|file| self.writer(Storage::Replays, file));
+        async move {
+            let mut w = pin!(writer?.await?);
+            w!(w, InputRecording: recording)?;
+            w.close().await
+        }
+    }
+}
+
+/// Whether players are driven by live input, recorded, or driven by a recording in place of live
+/// input.
+#[derive(Resource, Clone, Default, Debug)]
+pub enum InputReplay {
+    #[default]
+    Live,
+    Recording(InputRecording),
+    /// Replays `recording`, starting the update after [`Self::replay`] is called, once time is set
+    /// to advance by the first frame's delta. Time goes back to
+    /// [`TimeUpdateStrategy::Automatic`] afterwards.
+    Replaying {
+        recording: InputRecording,
+        frame: usize,
+        /// Whether time is already set to advance by the recorded deltas.
+        timed: bool,
+    },
+}
+
+impl InputReplay {
+    pub fn record(&mut self) {
+        *self = Self::Recording(default())
+    }
+
+    pub fn replay(&mut self, recording: InputRecording) {
+        *self = Self::Replaying {
+            recording,
+            frame: 0,
+            timed: false,
+        }
+    }
+
+    /// Goes back to live input, returning what was being recorded, if anything.
+    pub fn stop(&mut self) -> Option<InputRecording> {
+        match std::mem::take(self) {
+            Self::Recording(recording) => Some(recording),
+            Self::Live | Self::Replaying { .. } => None,
+        }
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        matches!(self, Self::Replaying { .. })
+    }
+}
+
+/// Sent when a replay ends and players go back to live input.
+#[derive(Event, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ReplayFinished {
+    /// Every recorded frame was replayed.
+    Completed,
+    /// A player is playing without recorded input, or has recorded input but isn't playing.
+    PlayerMismatch { player: u8 },
+    /// The recording was made with a different [`Time<Fixed>`] timestep.
+    TimestepMismatch { recorded: Duration, current: Duration },
+}
+
+pub struct ReplayPlugin;
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputReplay>()
+            .add_event::<ReplayFinished>()
+            .add_systems(
+                PreUpdate,
+                record_or_replay
+                    .run_if(|replay: Res<InputReplay>| !matches!(*replay, InputReplay::Live))
+                    .in_set(InputManagerSystem::ManualControl)
+                    .after(aim_at_cursor)
+                    .before(RouteSplitActions),
+            )
+            .add_systems(
+                RunFixedMainLoop,
+                replay_fixed
+                    .run_if(|replay: Res<InputReplay>| replay.is_replaying())
+                    .in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop)
+                    .after(update_action_state::<Controller>)
+                    .after(aim_at_cursor)
+                    .before(RouteSplitActions),
+            );
+    }
+}
+
+type ReplayTime<'a> = (Res<'a, Time<Real>>, Res<'a, Time<Fixed>>, ResMut<'a, TimeUpdateStrategy>);
+
+fn record_or_replay(
+    mut replay: ResMut<InputReplay>,
+    (real, fixed, mut strategy): ReplayTime,
+    mut players: Query<(&Player, &mut ActionState<Controller>)>,
+    mut finished: EventWriter<ReplayFinished>,
+) {
+    let end = match &mut *replay {
+        InputReplay::Live => None,
+        InputReplay::Recording(recording) => {
+            if recording.frames.is_empty() {
+                recording.timestep = fixed.timestep()
+            }
+
+            recording.frames.push(RecordedFrame {
+                delta: real.delta(),
+                players: players
+                    .iter()
+                    .map(|(&Player(player), state)| (player, ControllerFrame::capture(state)))
+                    .collect(),
+            });
+            None
+        }
+        InputReplay::Replaying { recording, frame, timed } => {
+            let end = if !std::mem::replace(timed, true) {
+                (recording.timestep != fixed.timestep()).then_some(ReplayFinished::TimestepMismatch {
+                    recorded: recording.timestep,
+                    current: fixed.timestep(),
+                })
+            } else {
+                match recording.frames.get(*frame) {
+                    Some(recorded) => match mismatched_player(recorded, &players) {
+                        Some(player) => Some(ReplayFinished::PlayerMismatch { player }),
+                        None => {
+                            for (&Player(player), mut state) in &mut players {
+                                recorded.players[&player].apply(&mut state)
+                            }
+
+                            *frame += 1;
+                            None
+                        }
+                    },
+                    None => Some(ReplayFinished::Completed),
+                }
+            };
+
+            // Time is updated at the start of the frame, so set up the next frame's delta ahead of time.
+            if end.is_none() &&
+                let Some(next) = recording.frames.get(*frame)
+            {
+                *strategy = TimeUpdateStrategy::ManualDuration(next.delta)
+            }
+
+            end
+        }
+    };
+
+    if let Some(end) = end {
+        *replay = InputReplay::Live;
+        *strategy = TimeUpdateStrategy::Automatic;
+        finished.send(end);
+    }
+}
+
+/// Applies the frame [`record_or_replay`] just replayed again, once the action states are
+/// recomputed from live input for [`FixedMain`](bevy::app::FixedMain).
+fn replay_fixed(replay: Res<InputReplay>, mut players: Query<(&Player, &mut ActionState<Controller>)>) {
+    let InputReplay::Replaying { recording, frame, .. } = &*replay else { return };
+    let Some(recorded) = frame.checked_sub(1).and_then(|frame| recording.frames.get(frame)) else {
+        return
+    };
+
+    if mismatched_player(recorded, &players).is_some() {
+        return
+    }
+
+    for (&Player(player), mut state) in &mut players {
+        recorded.players[&player].apply(&mut state)
+    }
+}
+
+/// A player that is either playing or recorded in `recorded`, but not both.
+fn mismatched_player(recorded: &RecordedFrame, players: &Query<(&Player, &mut ActionState<Controller>)>) -> Option<u8> {
+    let playing = players.iter().map(|(&Player(player), ..)| player).collect::<BTreeSet<_>>();
+    playing
+        .symmetric_difference(&recorded.players.keys().copied().collect())
+        .next()
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, process};
+
+    use async_fs::remove_dir_all;
+    use bevy::tasks::block_on;
+
+    use super::*;
+
+    #[test]
+    fn record_and_replay() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, ReplayPlugin));
+
+        let player = app.world_mut().spawn(Player(0)).id();
+        app.world_mut().resource_mut::<InputReplay>().record();
+
+        let mut state = app.world_mut().get_mut::<ActionState<Controller>>(player).unwrap();
+        state.press(&Controller::Jump);
+        state.set_axis_pair(&Controller::Move, Vec2::X);
+        app.update();
+
+        let mut state = app.world_mut().get_mut::<ActionState<Controller>>(player).unwrap();
+        state.release(&Controller::Jump);
+        app.update();
+
+        let recording = app.world_mut().resource_mut::<InputReplay>().stop().unwrap();
+        assert_eq!(recording.frames.len(), 2);
+
+        let root = temp_dir().join(format!("centripetal-replay-{}", process::id()));
+        let storage = LocalStorage::at(&root);
+        let recording = block_on(async {
+            storage.write_replay("test", recording.clone()).await.unwrap();
+            let read = storage.read_replay("test").await.unwrap();
+            remove_dir_all(&root).await.unwrap();
+            read
+        });
+
+        *app.world_mut().get_mut::<ActionState<Controller>>(player).unwrap() = default();
+        app.world_mut().resource_mut::<InputReplay>().replay(recording.clone());
+
+        // Sets up time for the first frame.
+        app.update();
+        assert_eq!(
+            *app.world().get::<ActionState<Controller>>(player).unwrap(),
+            default(),
+            "nothing replayed yet"
+        );
+
+        for frame in &recording.frames {
+            app.update();
+            let state = app.world().get::<ActionState<Controller>>(player).unwrap();
+            assert_eq!(ControllerFrame::capture(state), frame.players[&0]);
+            assert_eq!(app.world().resource::<Time<Real>>().delta(), frame.delta);
+        }
+
+        app.update();
+        assert!(!app.world().resource::<InputReplay>().is_replaying());
+        assert!(matches!(
+            app.world().resource::<TimeUpdateStrategy>(),
+            TimeUpdateStrategy::Automatic
+        ));
+
+        let events = app.world().resource::<Events<ReplayFinished>>();
+        assert_eq!(events.get_cursor().read(events).collect::<Vec<_>>(), [
+            &ReplayFinished::Completed
+        ]);
+    }
+
+    #[test]
+    fn replays_by_player_number() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, ReplayPlugin));
+
+        let first = app.world_mut().spawn(Player(0)).id();
+        let second = app.world_mut().spawn(Player(1)).id();
+        app.world_mut().resource_mut::<InputReplay>().record();
+        app.world_mut()
+            .get_mut::<ActionState<Controller>>(first)
+            .unwrap()
+            .press(&Controller::Jump);
+        app.update();
+        let recording = app.world_mut().resource_mut::<InputReplay>().stop().unwrap();
+
+        // Respawned so they reuse each other's entity indices, and no longer sort by player number.
+        app.world_mut().despawn(second);
+        app.world_mut().despawn(first);
+        let second = app.world_mut().spawn(Player(1)).id();
+        let first = app.world_mut().spawn(Player(0)).id();
+        assert!(second < first);
+
+        app.world_mut().resource_mut::<InputReplay>().replay(recording);
+        app.update();
+        app.update();
+
+        let pressed = |player| {
+            app.world()
+                .get::<ActionState<Controller>>(player)
+                .unwrap()
+                .pressed(&Controller::Jump)
+        };
+        assert!(pressed(first));
+        assert!(!pressed(second));
+    }
+
+    #[test]
+    fn ends_on_mismatch() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, ReplayPlugin));
+        let player = app.world_mut().spawn(Player(0)).id();
+
+        let timestep = app.world().resource::<Time<Fixed>>().timestep();
+        let frame = RecordedFrame {
+            delta: Duration::from_millis(16),
+            players: BTreeMap::from([(0, ControllerFrame::capture(&default()))]),
+        };
+        let recording = InputRecording {
+            timestep,
+            frames: vec![frame.clone(), frame],
+        };
+
+        let finished = |app: &mut App| {
+            app.world_mut()
+                .resource_mut::<Events<ReplayFinished>>()
+                .drain()
+                .collect::<Vec<_>>()
+        };
+
+        // A second player has no recorded input to replay.
+        app.world_mut().spawn(Player(1));
+        app.world_mut()
+            .get_mut::<ActionState<Controller>>(player)
+            .unwrap()
+            .press(&Controller::Jump);
+        app.world_mut().resource_mut::<InputReplay>().replay(recording.clone());
+        app.update();
+        app.update();
+        assert!(!app.world().resource::<InputReplay>().is_replaying());
+        assert_eq!(finished(&mut app), [ReplayFinished::PlayerMismatch { player: 1 }]);
+        assert!(
+            app.world()
+                .get::<ActionState<Controller>>(player)
+                .unwrap()
+                .pressed(&Controller::Jump),
+            "live input is left alone"
+        );
+
+        let recording = InputRecording {
+            timestep: timestep * 2,
+            ..recording
+        };
+        app.world_mut().resource_mut::<InputReplay>().replay(recording);
+        app.update();
+        assert_eq!(finished(&mut app), [ReplayFinished::TimestepMismatch {
+            recorded: timestep * 2,
+            current: timestep
+        }]);
+    }
+}
@@ -0,0 +1,151 @@
+use std::marker::PhantomData;
+
+use bevy::{app::RunFixedMainLoopSystem, prelude::*, reflect::GetTypeRegistration};
+use leafwing_input_manager::{
+    InputControlKind,
+    plugin::InputManagerSystem,
+    prelude::*,
+    systems::{swap_to_fixed_update, update_action_state},
+};
+
+/// An action set mirroring a subset of master action set `M`, so systems can query only the actions
+/// they care about while every action is still bound and rebound through `M`'s single input map.
+pub trait SplitAction<M: Actionlike>: Actionlike + Copy + TypePath + GetTypeRegistration {
+    /// Master actions mirrored by this set, each paired with the action its state is copied into.
+    const ROUTES: &'static [(M, Self)];
+}
+
+/// Where master action states are copied into their [`SplitAction`]s: within
+/// [`InputManagerSystem::ManualControl`] in [`PreUpdate`], and again once the master action states
+/// are recomputed for [`FixedMain`](bevy::app::FixedMain) in [`RunFixedMainLoop`]. Systems writing
+/// master action states should run before it in both schedules.
+#[derive(SystemSet, Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct RouteSplitActions;
+
+/// A set of [`SplitAction`]s of master action set `M`, implemented for tuples of them.
+pub trait SplitActions<M: Actionlike> {
+    fn register(app: &mut App);
+}
+
+/// A master action set, listing every [`SplitAction`] it routes into.
+pub trait MasterAction: Actionlike + Copy + TypePath + GetTypeRegistration {
+    type Split: SplitActions<Self>;
+}
+
+/// Registers `M` and all of its [`SplitAction`]s, requiring their states wherever `M`'s is, and
+/// copies `M`'s state into them in [`RouteSplitActions`].
+pub struct SplitActionPlugin<M: MasterAction>(PhantomData<fn() -> M>);
+impl<M: MasterAction> Default for SplitActionPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: MasterAction> Plugin for SplitActionPlugin<M> {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(InputManagerPlugin::<M>::default())
+            .configure_sets(PreUpdate, RouteSplitActions.in_set(InputManagerSystem::ManualControl))
+            .configure_sets(
+                RunFixedMainLoop,
+                RouteSplitActions
+                    .in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop)
+                    .after(update_action_state::<M>),
+            );
+        M::Split::register(app)
+    }
+}
+
+fn register_split<M: Actionlike + Copy, S: SplitAction<M>>(app: &mut App) {
+    for (from, to) in S::ROUTES {
+        let (from_kind, to_kind) = (from.input_control_kind(), to.input_control_kind());
+        assert_eq!(
+            from_kind, to_kind,
+            "{from:?} ({from_kind:?}) can't be routed into {to:?} ({to_kind:?})"
+        );
+    }
+
+    app.add_plugins(InputManagerPlugin::<S>::default())
+        .register_required_components::<ActionState<M>, ActionState<S>>()
+        .add_systems(PreUpdate, route_split::<M, S>.in_set(RouteSplitActions))
+        .add_systems(
+            RunFixedMainLoop,
+            route_split::<M, S>.in_set(RouteSplitActions).after(swap_to_fixed_update::<S>),
+        );
+}
+
+fn route_split<M: Actionlike + Copy, S: SplitAction<M>>(mut query: Query<(&ActionState<M>, &mut ActionState<S>)>) {
+    for (master, mut split) in &mut query {
+        for &(from, to) in S::ROUTES {
+            match from.input_control_kind() {
+                InputControlKind::Button => {
+                    if let Some(data) = master.button_data(&from) {
+                        split.set_button_data(to, data.clone())
+                    }
+                }
+                InputControlKind::Axis => {
+                    if let Some(data) = master.axis_data(&from) {
+                        split.set_value(&to, data.value)
+                    }
+                }
+                InputControlKind::DualAxis => {
+                    if let Some(data) = master.dual_axis_data(&from) {
+                        split.set_axis_pair(&to, data.pair)
+                    }
+                }
+                InputControlKind::TripleAxis => {
+                    if let Some(data) = master.triple_axis_data(&from) {
+                        split.set_axis_triple(&to, data.triple)
+                    }
+                }
+            }
+        }
+    }
+}
+
+macro_rules! impl_split_actions {
+    ($(($($name:ident)*))*) => {
+        $(
+            impl<M: Actionlike + Copy, $($name: SplitAction<M>),*> SplitActions<M> for ($($name,)*) {
+                fn register(app: &mut App) {
+                    $(register_split::<M, $name>(app);)*
+                }
+            }
+        )*
+    };
+}
+
+impl_split_actions!(
+    (A)
+    (A B)
+    (A B C)
+    (A B C D)
+    (A B C D E)
+    (A B C D E F)
+    (A B C D E F G)
+    (A B C D E F G H)
+);
+
+#[cfg(test)]
+mod tests {
+    use bevy::input::InputPlugin;
+
+    use super::*;
+    use crate::{Attack, Controller, Jump, Player, control::Move};
+
+    #[test]
+    fn routes_into_split_actions() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, InputPlugin, SplitActionPlugin::<Controller>::default()));
+
+        let player = app.world_mut().spawn(Player(0)).id();
+        let mut keys = app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        keys.press(KeyCode::Space);
+        keys.press(KeyCode::KeyD);
+        app.update();
+
+        let world = app.world();
+        assert!(world.get::<ActionState<Jump>>(player).unwrap().pressed(&Jump));
+        assert!(!world.get::<ActionState<Attack>>(player).unwrap().pressed(&Attack::Primary));
+        assert_eq!(world.get::<ActionState<Move>>(player).unwrap().axis_pair(&Move), Vec2::X);
+    }
+}
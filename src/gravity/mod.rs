@@ -0,0 +1,287 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+
+/// How a [`GravityField`]'s pull weakens between its [`surface`](GravityField::surface) and
+/// [`reach`](GravityField::reach).
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum Falloff {
+    /// Full strength everywhere within reach.
+    Constant,
+    /// Weakens linearly, down to nothing at the field's reach.
+    Linear,
+    /// Weakens with the square of the distance, like actual gravity.
+    #[default]
+    InverseSquare,
+}
+
+/// Pulls bodies with [`LocalGravity`] towards this entity's position.
+#[derive(Component, Copy, Clone, Debug)]
+pub struct GravityField {
+    /// Acceleration at the surface and anywhere closer.
+    pub strength: f32,
+    /// Distance up to which the pull is at full strength, typically the attractor's own radius.
+    pub surface: f32,
+    /// Distance beyond which there is no pull at all.
+    pub reach: f32,
+    /// Width of the band just inside [`reach`](Self::reach) across which the pull fades out and the
+    /// uniform [`Gravity`] fades in, so bodies crossing it aren't yanked around. Zero cuts off
+    /// abruptly.
+    pub edge: f32,
+    pub falloff: Falloff,
+}
+
+impl GravityField {
+    /// Fraction of their reach that [`point`](Self::point) and [`planetoid`] fields fade out
+    /// across.
+    pub const DEFAULT_EDGE: f32 = 0.2;
+
+    /// A field pulling with `strength` anywhere within `reach`, without any surface.
+    pub const fn point(strength: f32, reach: f32, falloff: Falloff) -> Self {
+        Self {
+            strength,
+            surface: 0.,
+            reach,
+            edge: reach * Self::DEFAULT_EDGE,
+            falloff,
+        }
+    }
+
+    /// How much the field holds sway at `distance` from its center: 1 up to its
+    /// [`edge`](Self::edge), fading to 0 at its [`reach`](Self::reach).
+    pub fn influence(&self, distance: f32) -> f32 {
+        if distance > self.reach {
+            0.
+        } else if self.edge <= f32::EPSILON {
+            1.
+        } else {
+            ((self.reach - distance) / self.edge).clamp(0., 1.)
+        }
+    }
+
+    /// Acceleration of a body at `point` due to a field centered at `center`.
+    pub fn acceleration(&self, center: Vec2, point: Vec2) -> Vec2 {
+        let offset = center - point;
+        let distance = offset.length();
+        let influence = self.influence(distance);
+        if influence <= 0. || distance <= f32::EPSILON {
+            return Vec2::ZERO
+        }
+
+        let scale = match self.falloff {
+            Falloff::Constant => 1.,
+            Falloff::Linear => {
+                1. - ((distance - self.surface) / (self.reach - self.surface).max(f32::EPSILON)).clamp(0., 1.)
+            }
+            Falloff::InverseSquare => {
+                let surface = self.surface.max(1.);
+                (surface / distance.max(surface)).powi(2)
+            }
+        };
+
+        offset / distance * self.strength * scale * influence
+    }
+}
+
+/// A static round body with a [`GravityField`] reaching `reach` times its radius, for characters to
+/// walk around.
+pub fn planetoid(radius: f32, strength: f32, reach: f32) -> impl Bundle {
+    (RigidBody::Static, Collider::circle(radius), GravityField {
+        strength,
+        surface: radius,
+        reach: radius * reach,
+        edge: radius * reach * GravityField::DEFAULT_EDGE,
+        falloff: Falloff::InverseSquare,
+    })
+}
+
+/// Makes a body fall along the sum of all [`GravityField`]s reaching it instead of the uniform
+/// [`Gravity`], which is only used where no field reaches, and blended in across the fields' edges.
+#[derive(Component, Copy, Clone, Debug)]
+#[require(GravityScale(|| GravityScale(0.)))]
+pub struct LocalGravity {
+    acceleration: Vec2,
+    up: Dir2,
+}
+
+impl LocalGravity {
+    /// Gravity already pulling along `acceleration`, until [`GravitySystem::Compute`] next runs.
+    pub fn new(acceleration: Vec2) -> Self {
+        Self {
+            acceleration,
+            up: Dir2::new(-acceleration).unwrap_or(Dir2::Y),
+        }
+    }
+
+    pub fn acceleration(&self) -> Vec2 {
+        self.acceleration
+    }
+
+    /// Opposite of the acceleration, or the previous up direction while weightless.
+    pub fn up(&self) -> Dir2 {
+        self.up
+    }
+
+    /// Perpendicular to [`up`](Self::up), clockwise, i.e. what `+X` input moves along.
+    pub fn right(&self) -> Dir2 {
+        Dir2::new_unchecked(Vec2::new(self.up.y, -self.up.x))
+    }
+}
+
+impl Default for LocalGravity {
+    fn default() -> Self {
+        Self {
+            acceleration: Vec2::ZERO,
+            up: Dir2::Y,
+        }
+    }
+}
+
+#[derive(SystemSet, Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum GravitySystem {
+    /// Computes every [`LocalGravity`].
+    Compute,
+    /// Accelerates bodies by their [`LocalGravity`].
+    Apply,
+}
+
+pub struct GravityPlugin;
+impl Plugin for GravityPlugin {
+    fn build(&self, app: &mut App) {
+        app.configure_sets(FixedUpdate, (GravitySystem::Compute, GravitySystem::Apply).chain())
+            .add_systems(
+                FixedUpdate,
+                (
+                    compute_local_gravity.in_set(GravitySystem::Compute),
+                    apply_local_gravity.in_set(GravitySystem::Apply),
+                ),
+            );
+    }
+}
+
+fn compute_local_gravity(
+    gravity: Res<Gravity>,
+    fields: Query<(&GravityField, &GlobalTransform)>,
+    mut bodies: Query<(&Position, &mut LocalGravity)>,
+) {
+    for (&Position(point), mut local) in &mut bodies {
+        // How much the strongest-held field overrides the uniform gravity.
+        let mut influence = 0f32;
+        let mut acceleration = Vec2::ZERO;
+
+        for (field, transform) in &fields {
+            let center = transform.translation().truncate();
+            influence = influence.max(field.influence(center.distance(point)));
+            acceleration += field.acceleration(center, point)
+        }
+
+        acceleration += gravity.0 * (1. - influence);
+
+        local.acceleration = acceleration;
+        if let Ok(down) = Dir2::new(acceleration) {
+            local.up = -down
+        }
+    }
+}
+
+fn apply_local_gravity(time: Res<Time>, mut bodies: Query<(&RigidBody, &LocalGravity, &mut LinearVelocity)>) {
+    let dt = time.delta_secs();
+    for (body, local, mut velocity) in &mut bodies {
+        if body.is_dynamic() {
+            velocity.0 += local.acceleration * dt
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{ecs::system::RunSystemOnce, time::TimeUpdateStrategy};
+
+    use super::*;
+
+    #[test]
+    fn falloff() {
+        let field = |falloff| GravityField {
+            strength: 100.,
+            surface: 10.,
+            reach: 50.,
+            edge: 10.,
+            falloff,
+        };
+
+        let at = |falloff, x: f32| field(falloff).acceleration(Vec2::ZERO, Vec2::new(x, 0.));
+        assert_eq!(at(Falloff::Constant, 40.), Vec2::new(-100., 0.));
+        assert_eq!(at(Falloff::Linear, 30.), Vec2::new(-50., 0.));
+        assert_eq!(at(Falloff::InverseSquare, 5.), Vec2::new(-100., 0.));
+        assert_eq!(at(Falloff::InverseSquare, 20.), Vec2::new(-25., 0.));
+        assert_eq!(at(Falloff::Constant, 45.), Vec2::new(-50., 0.), "halfway across the edge");
+        assert_eq!(at(Falloff::Constant, 60.), Vec2::ZERO);
+    }
+
+    #[test]
+    fn crosses_edge_smoothly() {
+        let mut world = World::new();
+        world.insert_resource(Gravity(Vec2::NEG_Y * 100.));
+        world.spawn((GravityField::point(100., 100., Falloff::Constant), GlobalTransform::default()));
+        let body = world.spawn((Position::default(), LocalGravity::default())).id();
+
+        let mut at = |x: f32| {
+            world.get_mut::<Position>(body).unwrap().0 = Vec2::new(x, 0.);
+            world.run_system_once(compute_local_gravity).unwrap();
+            *world.get::<LocalGravity>(body).unwrap()
+        };
+
+        assert_eq!(at(70.).acceleration(), Vec2::new(-100., 0.));
+        assert_eq!(at(130.).acceleration(), Vec2::new(0., -100.));
+
+        // Halfway across the edge, the field and the uniform gravity pull equally.
+        assert!(at(90.).up().angle_to(Vec2::ONE).abs() < 1e-5);
+
+        let mut previous = at(70.).acceleration();
+        for x in 71..=130 {
+            let acceleration = at(x as f32).acceleration();
+            assert!(acceleration.distance(previous) < 10., "jumped at {x}");
+            previous = acceleration
+        }
+    }
+
+    #[test]
+    fn blends_and_orients() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TransformPlugin, PhysicsPlugins::default(), GravityPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_micros(15625)))
+            .insert_resource(Gravity(Vec2::NEG_Y * 100.));
+
+        let field = GravityField::point(100., 100., Falloff::Constant);
+        app.world_mut().spawn((field, Transform::from_xyz(-50., 0., 0.)));
+        app.world_mut().spawn((field, Transform::from_xyz(50., 0., 0.)));
+
+        let body = |app: &mut App, x: f32, y: f32| {
+            app.world_mut()
+                .spawn((
+                    RigidBody::Dynamic,
+                    Collider::circle(1.),
+                    LocalGravity::default(),
+                    Transform::from_xyz(x, y, 0.),
+                ))
+                .id()
+        };
+
+        let between = body(&mut app, 0., 0.);
+        let right = body(&mut app, 120., 0.);
+        let outside = body(&mut app, 0., 500.);
+        for _ in 0..4 {
+            app.update()
+        }
+
+        let local = |app: &App, e| *app.world().get::<LocalGravity>(e).unwrap();
+        assert!(
+            local(&app, between).acceleration().length() < 1e-3,
+            "opposite fields cancel out"
+        );
+        assert_eq!(local(&app, right).up(), Dir2::X);
+        assert_eq!(local(&app, right).right(), Dir2::NEG_Y);
+        assert_eq!(local(&app, outside).acceleration(), Vec2::NEG_Y * 100.);
+    }
+}
@@ -14,26 +14,32 @@ use mimalloc_redirect::MiMalloc;
 #[global_allocator]
 static ALLOC: MiMalloc = MiMalloc;
 
+mod camera;
+mod character;
 mod control;
+mod gravity;
 mod storage;
+pub use camera::*;
+pub use character::*;
 pub use control::*;
+pub use gravity::*;
 pub use storage::*;
 
 pub mod persist;
 
-#[derive(Component, Copy, Clone, Default, Debug)]
-#[require(Camera2d)]
-pub struct PrimaryCamera;
-
-pub fn run() {
+pub fn run(root: StorageRoot) {
     App::new()
         .add_plugins((
             DefaultPlugins,
             PhysicsPlugins::default(),
             hephae! { .. },
             ControlPlugins,
-            StoragePlugin,
+            GravityPlugin,
+            CharacterPlugin,
+            CameraPlugin,
+            StoragePlugin { root, ..default() },
         ))
+        .insert_resource(Gravity(Vec2::NEG_Y * 1400.))
         .add_systems(Startup, on_startup)
         .run();
 }
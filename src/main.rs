@@ -1,3 +1,16 @@
-fn main() {
-    centripetal::run()
+use std::process::ExitCode;
+
+use centripetal::StorageRoot;
+
+fn main() -> ExitCode {
+    match StorageRoot::from_env() {
+        Ok(root) => {
+            centripetal::run(root);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -1,10 +1,13 @@
 use std::{
     borrow::Borrow,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     future::poll_fn,
+    hash::{BuildHasher, Hash},
     io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
     mem::MaybeUninit,
     pin::Pin,
     ptr::slice_from_raw_parts_mut,
+    time::Duration,
 };
 
 use bevy::{
@@ -121,14 +124,51 @@ impl<W: AsyncWrite + ConditionalSend> PersistWriter<W> {
     }
 }
 
-pub struct PersistReader<R: AsyncRead + ConditionalSend>(R);
+/// Bounds on what a [`PersistReader`] may allocate, so a corrupted or hostile length prefix can't
+/// request gigabytes of memory or recurse until the stack overflows.
+#[derive(Copy, Clone, Debug)]
+pub struct PersistLimits {
+    /// Total bytes that length-prefixed reads may allocate over the reader's lifetime.
+    pub max_bytes: usize,
+    /// Maximum element count of a single length prefix.
+    pub max_len: usize,
+    /// Maximum nesting depth of collections, options and boxes.
+    pub max_depth: usize,
+}
+
+impl Default for PersistLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 64 * 1024 * 1024,
+            max_len: 1 << 24,
+            max_depth: 64,
+        }
+    }
+}
+
+pub struct PersistReader<R: AsyncRead + ConditionalSend> {
+    reader: R,
+    limits: PersistLimits,
+    allocated: usize,
+    depth: usize,
+}
+
 impl<R: AsyncRead + ConditionalSend> PersistReader<R> {
     pub fn new(reader: R) -> Self {
-        Self(reader)
+        Self::with_limits(reader, PersistLimits::default())
+    }
+
+    pub fn with_limits(reader: R, limits: PersistLimits) -> Self {
+        Self {
+            reader,
+            limits,
+            allocated: 0,
+            depth: 0,
+        }
     }
 
     pub fn read(self: Pin<&mut Self>, mut buffer: &mut [u8]) -> impl ConditionalSendFuture<Output = IoResult<()>> {
-        let mut reader = unsafe { self.map_unchecked_mut(|s| &mut s.0) };
+        let mut reader = unsafe { self.map_unchecked_mut(|s| &mut s.reader) };
         async move {
             while !buffer.is_empty() {
                 let read = poll_fn(|ctx| reader.as_mut().poll_read(ctx, &mut buffer)).await?;
@@ -143,6 +183,54 @@ impl<R: AsyncRead + ConditionalSend> PersistReader<R> {
         }
     }
 
+    /// Reads a `usize` length prefix of `T` elements, charging `len * size_of::<T>()` bytes against the
+    /// allocation budget before the caller allocates anything.
+    pub fn read_len<T>(mut self: Pin<&mut Self>) -> impl ConditionalSendFuture<Output = IoResult<usize>> {
+        async move {
+            let len = r!(self, usize)?;
+            if len > self.limits.max_len {
+                return Err(IoError::new(
+                    IoErrorKind::InvalidData,
+                    format!("Length exceeded limit: {len} > {}", self.limits.max_len),
+                ))
+            }
+
+            let this = unsafe { self.get_unchecked_mut() };
+            match len
+                .checked_mul(size_of::<T>())
+                .and_then(|bytes| this.allocated.checked_add(bytes))
+            {
+                Some(allocated) if allocated <= this.limits.max_bytes => {
+                    this.allocated = allocated;
+                    Ok(len)
+                }
+                _ => Err(IoError::new(
+                    IoErrorKind::InvalidData,
+                    format!("Allocation exceeded limit of {} bytes", this.limits.max_bytes),
+                )),
+            }
+        }
+    }
+
+    /// Enters a nested value, failing if that exceeds the maximum depth. Pair with [`Self::exit`].
+    pub fn enter(self: Pin<&mut Self>) -> IoResult<()> {
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.depth >= this.limits.max_depth {
+            return Err(IoError::new(
+                IoErrorKind::InvalidData,
+                format!("Nesting exceeded limit: {}", this.limits.max_depth),
+            ))
+        }
+
+        this.depth += 1;
+        Ok(())
+    }
+
+    pub fn exit(self: Pin<&mut Self>) {
+        let this = unsafe { self.get_unchecked_mut() };
+        this.depth = this.depth.saturating_sub(1);
+    }
+
     pub fn de<'de, T: 'de + ConditionalSend>(
         mut self: Pin<&mut Self>,
         buffer: &'de mut Vec<u8>,
@@ -150,7 +238,7 @@ impl<R: AsyncRead + ConditionalSend> PersistReader<R> {
         + ConditionalSend,
     ) -> impl ConditionalSendFuture<Output = IoResult<T>> {
         async move {
-            let len = r!(self, usize)?;
+            let len = self.as_mut().read_len::<u8>().await?;
             buffer.reserve_exact(len);
 
             let off = buffer.len();
@@ -255,7 +343,7 @@ impl Persist for isize {
 
 impl Persist for String {
     async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
-        let len = r!(r, usize)?;
+        let len = r.as_mut().read_len::<u8>().await?;
         let mut this = vec![0; len];
 
         r.read(&mut this).await?;
@@ -270,13 +358,15 @@ impl Persist for String {
 
 impl<T: Persist> Persist for Vec<T> {
     async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
-        let len = r!(r, usize)?;
+        let len = r.as_mut().read_len::<T>().await?;
+        r.as_mut().enter()?;
 
         let mut this = Vec::with_capacity(len);
         for _ in 0..len {
             this.push(r!(r, T)?)
         }
 
+        r.exit();
         Ok(this)
     }
 
@@ -317,6 +407,314 @@ impl<const N: usize, T: Persist> Persist for [T; N] {
     }
 }
 
+impl Persist for bool {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        match r!(r, u8)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(IoError::new(IoErrorKind::InvalidData, format!("Invalid `bool`: {b}"))),
+        }
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, u8: *self as u8)
+    }
+}
+
+macro_rules! impl_persist_float {
+    ($($name:ty => $bits:ty)*) => {
+        $(
+            impl Persist for $name {
+                async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+                    Ok(Self::from_bits(r!(r, $bits)?))
+                }
+
+                async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+                    w!(w, $bits: self.to_bits())
+                }
+            }
+        )*
+    };
+}
+
+impl_persist_float!(
+    f32 => u32
+    f64 => u64
+);
+
+impl Persist for char {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        let num = r!(r, u32)?;
+        char::from_u32(num).ok_or_else(|| IoError::new(IoErrorKind::InvalidData, format!("Invalid `char`: {num:#x}")))
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, u32: *self as u32)
+    }
+}
+
+impl Persist for Duration {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        let secs = r!(r, u64)?;
+        let nanos = r!(r, u32)?;
+        if nanos >= 1_000_000_000 {
+            return Err(IoError::new(IoErrorKind::InvalidData, format!("Invalid `Duration` nanoseconds: {nanos}")))
+        }
+
+        Ok(Duration::new(secs, nanos))
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, u64: self.as_secs())?;
+        w!(w, u32: self.subsec_nanos())
+    }
+}
+
+impl<T: Persist> Persist for Box<T> {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        r.as_mut().enter()?;
+        let value = r!(r, T)?;
+
+        r.exit();
+        Ok(Box::new(value))
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, T: &**self)
+    }
+}
+
+impl<T: Persist> Persist for Option<T> {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        match r!(r, u8)? {
+            0 => Ok(None),
+            1 => {
+                r.as_mut().enter()?;
+                let value = r!(r, T)?;
+
+                r.exit();
+                Ok(Some(value))
+            }
+            tag => Err(IoError::new(IoErrorKind::InvalidData, format!("Invalid `Option` tag: {tag}"))),
+        }
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        match self {
+            None => w!(w, u8: 0),
+            Some(value) => {
+                w!(w, u8: 1)?;
+                w!(w, T: value)
+            }
+        }
+    }
+}
+
+impl<T: Persist, E: Persist> Persist for Result<T, E> {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        let tag = r!(r, u8)?;
+        r.as_mut().enter()?;
+
+        let value = match tag {
+            0 => Ok(r!(r, T)?),
+            1 => Err(r!(r, E)?),
+            tag => return Err(IoError::new(IoErrorKind::InvalidData, format!("Invalid `Result` tag: {tag}"))),
+        };
+
+        r.exit();
+        Ok(value)
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        match self {
+            Ok(value) => {
+                w!(w, u8: 0)?;
+                w!(w, T: value)
+            }
+            Err(error) => {
+                w!(w, u8: 1)?;
+                w!(w, E: error)
+            }
+        }
+    }
+}
+
+macro_rules! impl_persist_tuple {
+    ($(($($name:ident $index:tt)*))*) => {
+        $(
+            impl<$($name: Persist),*> Persist for ($($name,)*) {
+                #[allow(unused_mut, unused_variables)]
+                async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+                    Ok(($(r!(r, $name)?,)*))
+                }
+
+                #[allow(unused_mut, unused_variables)]
+                async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+                    $(w!(w, $name: &self.$index)?;)*
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+impl_persist_tuple!(
+    ()
+    (A 0)
+    (A 0 B 1)
+    (A 0 B 1 C 2)
+    (A 0 B 1 C 2 D 3)
+    (A 0 B 1 C 2 D 3 E 4)
+    (A 0 B 1 C 2 D 3 E 4 F 5)
+    (A 0 B 1 C 2 D 3 E 4 F 5 G 6)
+    (A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7)
+    (A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7 I 8)
+    (A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7 I 8 J 9)
+    (A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7 I 8 J 9 K 10)
+    (A 0 B 1 C 2 D 3 E 4 F 5 G 6 H 7 I 8 J 9 K 10 L 11)
+);
+
+impl<T: Persist> Persist for VecDeque<T> {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        let len = r.as_mut().read_len::<T>().await?;
+        r.as_mut().enter()?;
+
+        let mut this = VecDeque::with_capacity(len);
+        for _ in 0..len {
+            this.push_back(r!(r, T)?)
+        }
+
+        r.exit();
+        Ok(this)
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, usize: self.len())?;
+        for item in self {
+            w!(w, T: item)?
+        }
+
+        Ok(())
+    }
+}
+
+impl<K: Persist + Ord, V: Persist> Persist for BTreeMap<K, V> {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        let len = r.as_mut().read_len::<(K, V)>().await?;
+        r.as_mut().enter()?;
+
+        let mut this = BTreeMap::new();
+        for _ in 0..len {
+            let key = r!(r, K)?;
+            if this.insert(key, r!(r, V)?).is_some() {
+                return Err(IoError::new(IoErrorKind::InvalidData, "Duplicate map key"))
+            }
+        }
+
+        r.exit();
+        Ok(this)
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, usize: self.len())?;
+        for (key, value) in self {
+            w!(w, K: key)?;
+            w!(w, V: value)?
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Persist + Ord> Persist for BTreeSet<T> {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        let len = r.as_mut().read_len::<T>().await?;
+        r.as_mut().enter()?;
+
+        let mut this = BTreeSet::new();
+        for _ in 0..len {
+            if !this.insert(r!(r, T)?) {
+                return Err(IoError::new(IoErrorKind::InvalidData, "Duplicate set item"))
+            }
+        }
+
+        r.exit();
+        Ok(this)
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, usize: self.len())?;
+        for item in self {
+            w!(w, T: item)?
+        }
+
+        Ok(())
+    }
+}
+
+// Hash maps and sets are written in key order so that equal collections produce byte-identical files.
+impl<K: Persist + Ord + Hash, V: Persist, S: BuildHasher + Default + ConditionalSend + Sync + Clone> Persist
+    for HashMap<K, V, S>
+{
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        let len = r.as_mut().read_len::<(K, V)>().await?;
+        r.as_mut().enter()?;
+
+        let mut this = HashMap::with_capacity_and_hasher(len, S::default());
+        for _ in 0..len {
+            let key = r!(r, K)?;
+            if this.insert(key, r!(r, V)?).is_some() {
+                return Err(IoError::new(IoErrorKind::InvalidData, "Duplicate map key"))
+            }
+        }
+
+        r.exit();
+        Ok(this)
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        let mut entries = self.iter().collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|&(key, ..)| key);
+
+        w!(w, usize: entries.len())?;
+        for (key, value) in entries {
+            w!(w, K: key)?;
+            w!(w, V: value)?
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Persist + Ord + Hash, S: BuildHasher + Default + ConditionalSend + Sync + Clone> Persist for HashSet<T, S> {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        let len = r.as_mut().read_len::<T>().await?;
+        r.as_mut().enter()?;
+
+        let mut this = HashSet::with_capacity_and_hasher(len, S::default());
+        for _ in 0..len {
+            if !this.insert(r!(r, T)?) {
+                return Err(IoError::new(IoErrorKind::InvalidData, "Duplicate set item"))
+            }
+        }
+
+        r.exit();
+        Ok(this)
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        let mut items = self.iter().collect::<Vec<_>>();
+        items.sort_unstable();
+
+        w!(w, usize: items.len())?;
+        for item in items {
+            w!(w, T: item)?
+        }
+
+        Ok(())
+    }
+}
+
 pub trait PersistVersion<const VERSION: u16>: Persist {
     fn read_versioned<R: AsyncRead + ConditionalSend>(
         r: Pin<&mut PersistReader<R>>,
@@ -327,3 +725,343 @@ pub trait PersistVersion<const VERSION: u16>: Persist {
         w: Pin<&mut PersistWriter<W>>,
     ) -> impl ConditionalSendFuture<Output = IoResult<()>>;
 }
+
+/// Upgrades data written at version `FROM` by one version, used by `#[persist(migrate)]` so that old
+/// versions can be read into their own frozen shape instead of the current one.
+pub trait PersistMigrate<const FROM: u16>: Persist {
+    /// The shape of `Self` as written at version `FROM`, usually derived with `#[persist(auto)]`.
+    type Legacy: Persist;
+    /// The shape at version `FROM + 1`; `PersistMigrate::<{ FROM + 1 }>::Legacy`, or `Self` if that is
+    /// the current version.
+    type Next;
+
+    fn migrate(legacy: Self::Legacy) -> Self::Next;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fmt::Debug, pin::pin};
+
+    use bevy::{prelude::default, tasks::block_on};
+
+    use super::*;
+
+    fn read<T: Persist>(bytes: &[u8], limits: PersistLimits) -> IoResult<T> {
+        block_on(async {
+            let mut r = pin!(PersistReader::with_limits(bytes, limits));
+            r!(r, T)
+        })
+    }
+
+    fn write<T: Persist>(value: T) -> Vec<u8> {
+        block_on(async {
+            let mut bytes = Vec::new();
+            let mut w = pin!(PersistWriter::new(&mut bytes));
+            w!(w, T: value).unwrap();
+
+            bytes
+        })
+    }
+
+    #[test]
+    fn roundtrip_within_limits() {
+        let value = vec![vec![String::from("centripetal")], Vec::new()];
+        assert_eq!(read::<Vec<Vec<String>>>(&write(value.clone()), default()).unwrap(), value);
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = write(vec![1u32, 2, 3]);
+        for len in 0..bytes.len() {
+            let e = read::<Vec<u32>>(&bytes[..len], default()).unwrap_err();
+            assert_eq!(e.kind(), IoErrorKind::UnexpectedEof);
+        }
+    }
+
+    #[test]
+    fn malicious_length() {
+        let bytes = u32::MAX.to_le_bytes();
+        assert_eq!(read::<String>(&bytes, default()).unwrap_err().kind(), IoErrorKind::InvalidData);
+        assert_eq!(read::<Vec<u64>>(&bytes, default()).unwrap_err().kind(), IoErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn max_len() {
+        let limits = PersistLimits {
+            max_len: 2,
+            ..default()
+        };
+
+        assert!(read::<Vec<u8>>(&write(vec![1u8, 2]), limits).is_ok());
+        assert_eq!(
+            read::<Vec<u8>>(&write(vec![1u8, 2, 3]), limits).unwrap_err().kind(),
+            IoErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn max_bytes() {
+        let limits = PersistLimits {
+            max_bytes: 8,
+            ..default()
+        };
+
+        // Each prefix is charged separately, so two strings of 4 fit but a third byte doesn't.
+        let bytes = write((String::from("abcd"), String::from("efgh"), String::from("i")));
+        assert_eq!(
+            read::<(String, String, String)>(&bytes, limits).unwrap_err().kind(),
+            IoErrorKind::InvalidData
+        );
+        assert!(read::<(String, String)>(&bytes, limits).is_ok());
+    }
+
+    #[test]
+    fn max_depth() {
+        let limits = PersistLimits {
+            max_depth: 2,
+            ..default()
+        };
+
+        let bytes = write(vec![vec![vec![0u8]]]);
+        assert!(read::<Vec<Vec<Vec<u8>>>>(&bytes, default()).is_ok());
+        assert_eq!(
+            read::<Vec<Vec<Vec<u8>>>>(&bytes, limits).unwrap_err().kind(),
+            IoErrorKind::InvalidData
+        );
+
+        let bytes = write(Some(Some(Some(0u8))));
+        assert_eq!(
+            read::<Option<Option<Option<u8>>>>(&bytes, limits).unwrap_err().kind(),
+            IoErrorKind::InvalidData
+        );
+    }
+
+    fn roundtrip<T: Persist + Clone + PartialEq + Debug>(value: T) {
+        assert_eq!(read::<T>(&write(value.clone()), default()).unwrap(), value);
+    }
+
+    fn invalid<T: Persist + Debug>(bytes: &[u8]) {
+        assert_eq!(read::<T>(bytes, default()).unwrap_err().kind(), IoErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn primitives() {
+        roundtrip(false);
+        roundtrip(true);
+        roundtrip('c');
+        roundtrip('\u{1F300}');
+        roundtrip(Duration::new(u64::MAX, 999_999_999));
+        roundtrip(Duration::from_millis(1500));
+
+        invalid::<bool>(&[2]);
+        invalid::<char>(&0xD800u32.to_le_bytes());
+        invalid::<Duration>(&[0u64.to_le_bytes().as_slice(), &1_000_000_000u32.to_le_bytes()].concat());
+    }
+
+    #[test]
+    fn options_and_results() {
+        roundtrip(None::<u32>);
+        roundtrip(Some(String::from("centripetal")));
+        roundtrip(Ok::<u8, String>(3));
+        roundtrip(Err::<u8, String>("failed".into()));
+
+        invalid::<Option<u8>>(&[2, 0]);
+        invalid::<Result<u8, u8>>(&[2, 0]);
+    }
+
+    #[test]
+    fn tuples() {
+        assert!(write(()).is_empty());
+        roundtrip(());
+        roundtrip((1u8,));
+        roundtrip((1u8, String::from("two"), 3.0f32, Some('4')));
+        roundtrip((0u8, 1u16, 2u32, 3u64, 4i8, 5i16, 6i32, 7i64, 8usize, 9isize, true, 'b'));
+    }
+
+    #[test]
+    fn collections() {
+        roundtrip(VecDeque::from([3u8, 1, 2]));
+        roundtrip(BTreeMap::from([(String::from("b"), 2u8), (String::from("a"), 1)]));
+        roundtrip(BTreeSet::from([3u16, 1, 2]));
+        roundtrip(HashMap::<_, _>::from_iter([(2u8, String::from("b")), (1, String::from("a"))]));
+        roundtrip(HashSet::<_>::from_iter(['c', 'a', 'b']));
+
+        // Duplicates can only come from a corrupt or crafted file.
+        let bytes = write(vec![(1u8, 2u8), (1, 3)]);
+        invalid::<BTreeMap<u8, u8>>(&bytes);
+        invalid::<HashMap<u8, u8>>(&bytes);
+
+        let bytes = write(vec![1u8, 1]);
+        invalid::<BTreeSet<u8>>(&bytes);
+        invalid::<HashSet<u8>>(&bytes);
+    }
+
+    #[test]
+    fn hash_collections_are_sorted() {
+        let entries = (0..64u32).map(|i| (i, i.to_string())).collect::<Vec<_>>();
+        let forward = entries.iter().cloned().collect::<HashMap<_, _>>();
+        let backward = entries.iter().rev().cloned().collect::<HashMap<_, _>>();
+
+        let bytes = write(forward);
+        assert_eq!(bytes, write(backward));
+        assert_eq!(bytes, write(entries.iter().cloned().collect::<BTreeMap<_, _>>()));
+
+        let forward = (0..64u32).collect::<HashSet<_>>();
+        let backward = (0..64u32).rev().collect::<HashSet<_>>();
+        assert_eq!(write(forward), write(backward));
+    }
+
+    #[derive(Persist, Clone, PartialEq, Debug)]
+    #[persist(version = 0, auto)]
+    struct Named {
+        id: u32,
+        name: String,
+        // Shares its name with the generated writer.
+        w: Vec<u8>,
+    }
+
+    #[derive(Persist, Clone, PartialEq, Debug)]
+    #[persist(auto)]
+    struct Tuple(u8, Option<String>);
+
+    #[derive(Persist, Clone, PartialEq, Debug)]
+    #[persist(version = 0, auto)]
+    struct Unit;
+
+    #[test]
+    fn derive_structs() {
+        let named = Named {
+            id: 7,
+            name: "centripetal".into(),
+            w: vec![1, 2, 3],
+        };
+        let bytes = write(named.clone());
+        assert_eq!(bytes[..2], 0u16.to_le_bytes(), "versioned types start with their version");
+        assert_eq!(read::<Named>(&bytes, default()).unwrap(), named);
+
+        let tuple = Tuple(3, Some("slot".into()));
+        assert_eq!(write(tuple.clone()), write((3u8, Some(String::from("slot")))), "no version header");
+        assert_eq!(read::<Tuple>(&write(tuple.clone()), default()).unwrap(), tuple);
+
+        assert_eq!(write(Unit), 0u16.to_le_bytes());
+        assert_eq!(read::<Unit>(&write(Unit), default()).unwrap(), Unit);
+        assert_eq!(
+            read::<Unit>(&1u16.to_le_bytes(), default()).unwrap_err().kind(),
+            IoErrorKind::InvalidData
+        );
+    }
+
+    #[derive(Persist, Clone, PartialEq, Debug)]
+    #[persist(version = 0, auto)]
+    enum Tagged {
+        #[persist(tag = 0)]
+        Empty,
+        #[persist(tag = 5)]
+        Named { id: u32, name: String },
+        #[persist(tag = 6)]
+        Tuple(u8, Option<String>),
+        #[persist(tag = 2)]
+        Unit,
+    }
+
+    #[test]
+    fn derive_enums() {
+        let values = [
+            (Tagged::Empty, 0u16),
+            (
+                Tagged::Named {
+                    id: 7,
+                    name: "centripetal".into(),
+                },
+                5,
+            ),
+            (Tagged::Tuple(3, Some("slot".into())), 6),
+            (Tagged::Unit, 2),
+        ];
+
+        for (value, tag) in values {
+            let bytes = write(value.clone());
+            assert_eq!(bytes[2..4], tag.to_le_bytes(), "{value:?}");
+            assert_eq!(read::<Tagged>(&bytes, default()).unwrap(), value);
+        }
+
+        let e = read::<Tagged>(&[0, 0, 1, 0], default()).unwrap_err();
+        assert_eq!(e.kind(), IoErrorKind::InvalidData);
+        assert_eq!(e.to_string(), "Invalid tag: 1.");
+    }
+
+    #[derive(Persist, Clone, PartialEq, Debug)]
+    #[persist(auto)]
+    struct MigratedV0 {
+        count: u8,
+    }
+
+    #[derive(Persist, Clone, PartialEq, Debug)]
+    #[persist(auto)]
+    struct MigratedV1 {
+        count: u16,
+        name: String,
+    }
+
+    #[derive(Persist, Clone, PartialEq, Debug)]
+    #[persist(version = 2, auto, migrate)]
+    struct Migrated {
+        count: u32,
+        name: String,
+        enabled: bool,
+    }
+
+    impl PersistMigrate<0> for Migrated {
+        type Legacy = MigratedV0;
+        type Next = MigratedV1;
+
+        fn migrate(legacy: Self::Legacy) -> Self::Next {
+            MigratedV1 {
+                count: legacy.count.into(),
+                name: "unnamed".into(),
+            }
+        }
+    }
+
+    impl PersistMigrate<1> for Migrated {
+        type Legacy = MigratedV1;
+        type Next = Self;
+
+        fn migrate(legacy: Self::Legacy) -> Self::Next {
+            Self {
+                count: legacy.count.into(),
+                name: legacy.name,
+                enabled: true,
+            }
+        }
+    }
+
+    #[test]
+    fn derive_migrations() {
+        let v0 = write((0u16, MigratedV0 { count: 3 }));
+        assert_eq!(read::<Migrated>(&v0, default()).unwrap(), Migrated {
+            count: 3,
+            name: "unnamed".into(),
+            enabled: true,
+        });
+
+        let v1 = write((1u16, MigratedV1 {
+            count: 300,
+            name: "slot".into(),
+        }));
+        assert_eq!(read::<Migrated>(&v1, default()).unwrap(), Migrated {
+            count: 300,
+            name: "slot".into(),
+            enabled: true,
+        });
+
+        let current = Migrated {
+            count: 70000,
+            name: "current".into(),
+            enabled: false,
+        };
+        let bytes = write(current.clone());
+        assert_eq!(bytes[..2], 2u16.to_le_bytes());
+        assert_eq!(read::<Migrated>(&bytes, default()).unwrap(), current);
+    }
+}
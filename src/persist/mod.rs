@@ -1,17 +1,23 @@
-use std::{io::Result as IoResult, pin::Pin};
+use std::{
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    pin::Pin,
+};
 
+use avian2d::prelude::*;
 use bevy::{
+    color::{ColorToComponents, Hsla, Hsva, Hwba, Laba, Lcha, Oklaba, Oklcha, Xyza},
     prelude::*,
     tasks::futures_lite::{AsyncRead, AsyncWrite},
     utils::ConditionalSend,
 };
+use leafwing_input_manager::buttonlike::ButtonState;
 
 mod def;
 mod serde;
 pub use def::*;
 pub use serde::*;
 
-use crate::{de, ser};
+use crate::{de, r, ser, w};
 
 impl Persist for KeyCode {
     async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
@@ -22,3 +28,226 @@ impl Persist for KeyCode {
         ser!(w, KeyCode: self)
     }
 }
+
+impl Persist for GamepadButton {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        de!(r, GamepadButton)
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        ser!(w, GamepadButton: self)
+    }
+}
+
+impl Persist for ButtonState {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        de!(r, ButtonState)
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        ser!(w, ButtonState: self)
+    }
+}
+
+impl Persist for Vec2 {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        Ok(Self::from_array(r!(r, [f32; 2])?))
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, [f32; 2]: self.to_array())
+    }
+}
+
+impl Persist for Vec3 {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        Ok(Self::from_array(r!(r, [f32; 3])?))
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, [f32; 3]: self.to_array())
+    }
+}
+
+impl Persist for Quat {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        Ok(Self::from_array(r!(r, [f32; 4])?))
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, [f32; 4]: self.to_array())
+    }
+}
+
+impl Persist for Rot2 {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        let [cos, sin] = r!(r, [f32; 2])?;
+        Ok(Self { cos, sin })
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, [f32; 2]: [self.cos, self.sin])
+    }
+}
+
+impl Persist for Transform {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        Ok(Self {
+            translation: r!(r, Vec3)?,
+            rotation: r!(r, Quat)?,
+            scale: r!(r, Vec3)?,
+        })
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, Vec3: self.translation)?;
+        w!(w, Quat: self.rotation)?;
+        w!(w, Vec3: self.scale)
+    }
+}
+
+macro_rules! impl_persist_color {
+    ($($tag:literal => $space:ident)*) => {
+        // The color space is kept as a `u8` tag so colors read back exactly as they were written.
+        impl Persist for Color {
+            async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+                match r!(r, u8)? {
+                    $($tag => Ok(Self::$space($space::from_f32_array(r!(r, [f32; 4])?))),)*
+                    tag => Err(IoError::new(IoErrorKind::InvalidData, format!("Invalid `Color` tag: {tag}"))),
+                }
+            }
+
+            async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+                match *self {
+                    $(Self::$space(color) => {
+                        w!(w, u8: $tag)?;
+                        w!(w, [f32; 4]: color.to_f32_array())
+                    })*
+                }
+            }
+        }
+    };
+}
+
+impl_persist_color!(
+    0 => Srgba
+    1 => LinearRgba
+    2 => Hsla
+    3 => Hsva
+    4 => Hwba
+    5 => Laba
+    6 => Lcha
+    7 => Oklaba
+    8 => Oklcha
+    9 => Xyza
+);
+
+impl Persist for Name {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        Ok(Self::new(r!(r, String)?))
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, String: String::from(self.as_str()))
+    }
+}
+
+impl Persist for LinearVelocity {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        Ok(Self(r!(r, Vec2)?))
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, Vec2: self.0)
+    }
+}
+
+impl Persist for AngularVelocity {
+    async fn read<R: AsyncRead + ConditionalSend>(mut r: Pin<&mut PersistReader<R>>) -> IoResult<Self> {
+        Ok(Self(r!(r, f32)?))
+    }
+
+    async fn write<W: AsyncWrite + ConditionalSend>(&self, mut w: Pin<&mut PersistWriter<W>>) -> IoResult<()> {
+        w!(w, f32: self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fmt::Debug, pin::pin};
+
+    use bevy::tasks::block_on;
+
+    use super::*;
+
+    fn read<T: Persist>(bytes: &[u8]) -> IoResult<T> {
+        block_on(async {
+            let mut r = pin!(PersistReader::new(bytes));
+            r!(r, T)
+        })
+    }
+
+    fn write<T: Persist>(value: T) -> Vec<u8> {
+        block_on(async {
+            let mut bytes = Vec::new();
+            let mut w = pin!(PersistWriter::new(&mut bytes));
+            w!(w, T: value).unwrap();
+
+            bytes
+        })
+    }
+
+    fn roundtrip<T: Persist + Clone + PartialEq + Debug>(value: T) {
+        assert_eq!(read::<T>(&write(value.clone())).unwrap(), value);
+    }
+
+    #[test]
+    fn math() {
+        roundtrip(Vec2::new(1.5, -2.0));
+        roundtrip(Vec3::new(1.5, -2.0, 3.25));
+        roundtrip(Quat::from_rotation_z(0.5));
+        roundtrip(Rot2::radians(0.5));
+        roundtrip(
+            Transform::from_xyz(1.0, 2.0, 3.0)
+                .with_rotation(Quat::from_rotation_z(0.5))
+                .with_scale(Vec3::splat(2.0)),
+        );
+
+        assert_eq!(write(Vec2::new(1.5, -2.0)), write([1.5f32, -2.0]), "fixed layout");
+    }
+
+    #[test]
+    fn colors() {
+        let colors = [
+            Color::srgba(0.1, 0.2, 0.3, 0.4),
+            Color::linear_rgba(0.1, 0.2, 0.3, 0.4),
+            Color::hsla(120.0, 0.5, 0.25, 0.4),
+            Color::hsva(120.0, 0.5, 0.25, 0.4),
+            Color::hwba(120.0, 0.5, 0.25, 0.4),
+            Color::laba(0.5, 0.1, -0.1, 0.4),
+            Color::lcha(0.5, 0.1, 120.0, 0.4),
+            Color::oklaba(0.5, 0.1, -0.1, 0.4),
+            Color::oklcha(0.5, 0.1, 120.0, 0.4),
+            Color::xyza(0.1, 0.2, 0.3, 0.4),
+        ];
+
+        for (tag, color) in colors.into_iter().enumerate() {
+            let bytes = write(color);
+            assert_eq!(usize::from(bytes[0]), tag, "{color:?}");
+            assert_eq!(read::<Color>(&bytes).unwrap(), color);
+        }
+
+        let mut bytes = write(Color::WHITE);
+        bytes[0] = 10;
+        assert_eq!(read::<Color>(&bytes).unwrap_err().kind(), IoErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn components() {
+        roundtrip(Name::new("centripetal"));
+        assert_eq!(write(Name::new("centripetal")), write(String::from("centripetal")));
+
+        roundtrip(LinearVelocity(Vec2::new(3.0, -4.0)));
+        roundtrip(AngularVelocity(-1.5));
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    ffi::OsString,
+    io::{ErrorKind as IoErrorKind, Result as IoResult},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy::prelude::*;
+
+use crate::{StorageBackend, open_envelope};
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// The previous contents of `path`, kept around by [`write_atomic`].
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".bak")
+}
+
+/// Writes `bytes` to a temporary file next to `path`, syncs it to disk, then renames it over
+/// `path`. The file being replaced is first moved to [`backup_path`] if it's a valid envelope of
+/// the same kind as `bytes`, so a corrupt file never replaces the last good backup. The renames
+/// themselves aren't synced, so a crash may undo them, but each file is always either fully written
+/// or untouched.
+pub async fn write_atomic(backend: Arc<dyn StorageBackend>, path: PathBuf, bytes: Vec<u8>) -> IoResult<()> {
+    let magic = bytes.first_chunk::<4>().copied();
+    let temp = with_suffix(&path, ".tmp");
+    backend.write(&temp, bytes).await?;
+
+    let valid = match backend.read(&path).await {
+        Ok(previous) => magic.is_some_and(|magic| open_envelope(magic, previous).is_ok()),
+        Err(e) if e.kind() == IoErrorKind::NotFound => false,
+        Err(e) => return Err(e),
+    };
+
+    if valid {
+        backend.rename(&path, &backup_path(&path)).await?
+    }
+
+    backend.rename(&temp, &path).await
+}
+
+/// Reads and verifies the envelope at `path`, falling back to its [`backup_path`] if the primary
+/// file is missing or fails to verify. The primary file's error is returned if both fail.
+pub async fn read_with_backup(backend: Arc<dyn StorageBackend>, path: PathBuf, magic: [u8; 4]) -> IoResult<Vec<u8>> {
+    let primary = match backend.read(&path).await {
+        Ok(bytes) => open_envelope(magic, bytes),
+        Err(e) => Err(e),
+    };
+
+    match primary {
+        Ok(payload) => Ok(payload),
+        Err(e) => {
+            let backup = backup_path(&path);
+            match backend.read(&backup).await.and_then(|bytes| open_envelope(magic, bytes)) {
+                Ok(payload) => {
+                    warn!("Couldn't read {}, using backup {}: {e}", path.display(), backup.display());
+                    Ok(payload)
+                }
+                Err(..) => Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, process};
+
+    use async_fs::{remove_dir_all, write};
+    use bevy::tasks::block_on;
+
+    use super::*;
+    use crate::{FileBackend, Storage, seal_envelope};
+
+    #[test]
+    fn falls_back_to_backup() {
+        block_on(async {
+            let dir = temp_dir().join(format!("centripetal-atomic-{}", process::id()));
+            let path = dir.join("test.pref");
+            let magic = Storage::Settings.magic();
+            let backend: Arc<dyn StorageBackend> = Arc::new(FileBackend);
+
+            write_atomic(backend.clone(), path.clone(), seal_envelope(magic, b"old").unwrap())
+                .await
+                .unwrap();
+            write_atomic(backend.clone(), path.clone(), seal_envelope(magic, b"new").unwrap())
+                .await
+                .unwrap();
+            assert_eq!(read_with_backup(backend.clone(), path.clone(), magic).await.unwrap(), b"new");
+
+            write(&path, b"garbage").await.unwrap();
+            assert_eq!(read_with_backup(backend.clone(), path.clone(), magic).await.unwrap(), b"old");
+
+            // Writing over a corrupt file keeps the last good backup instead of rotating the corrupt file in.
+            write_atomic(backend.clone(), path.clone(), seal_envelope(magic, b"newer").unwrap())
+                .await
+                .unwrap();
+            assert_eq!(
+                open_envelope(magic, backend.read(&backup_path(&path)).await.unwrap()).unwrap(),
+                b"old"
+            );
+            assert_eq!(read_with_backup(backend, path.clone(), magic).await.unwrap(), b"newer");
+
+            remove_dir_all(dir).await.unwrap();
+        })
+    }
+}
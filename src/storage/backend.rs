@@ -0,0 +1,313 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use async_fs::{File, create_dir_all, metadata, read, read_dir, remove_dir_all, remove_file, rename};
+use bevy::{
+    tasks::futures_lite::{AsyncWriteExt, StreamExt},
+    utils::BoxedFuture,
+};
+
+/// A direct child of a directory, as listed by [`StorageBackend::list`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct StorageEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Where [`LocalStorage`](crate::LocalStorage) keeps its files. Paths are the ones it resolves
+/// files to under its settings, saves, and replays directories; backends without a real filesystem
+/// may treat them as plain keys. Missing files and directories are reported as
+/// [`IoErrorKind::NotFound`].
+pub trait StorageBackend: Debug + Send + Sync + 'static {
+    /// Opens the file at `path` and reads all of it.
+    fn read(&self, path: &Path) -> BoxedFuture<'static, IoResult<Vec<u8>>>;
+
+    /// Opens the file at `path` for writing, creating it and any missing parent directories, and
+    /// replaces its contents with `bytes`. Resolves once the contents are durable.
+    fn write(&self, path: &Path, bytes: Vec<u8>) -> BoxedFuture<'static, IoResult<()>>;
+
+    /// Lists the direct children of the directory at `path`.
+    fn list(&self, path: &Path) -> BoxedFuture<'static, IoResult<Vec<StorageEntry>>>;
+
+    /// Deletes the file at `path`, or the directory at `path` along with everything in it.
+    fn delete(&self, path: &Path) -> BoxedFuture<'static, IoResult<()>>;
+
+    /// Moves the file or directory at `from` to `to`, replacing `to` if both are files. Moving a
+    /// file onto a directory, or a directory onto a file or a directory with anything in it,
+    /// fails.
+    fn rename(&self, from: &Path, to: &Path) -> BoxedFuture<'static, IoResult<()>>;
+
+    /// Whether there is a file or directory at `path`. Only failing to check is an error, not a
+    /// missing file.
+    fn exists(&self, path: &Path) -> BoxedFuture<'static, IoResult<bool>>;
+}
+
+/// Stores files in the actual filesystem.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct FileBackend;
+impl StorageBackend for FileBackend {
+    fn read(&self, path: &Path) -> BoxedFuture<'static, IoResult<Vec<u8>>> {
+        Box::pin(read(path.to_owned()))
+    }
+
+    fn write(&self, path: &Path, bytes: Vec<u8>) -> BoxedFuture<'static, IoResult<()>> {
+        let path = path.to_owned();
+        Box::pin(async move {
+            if let Some(parent) = path.parent() {
+                create_dir_all(parent).await?;
+            }
+
+            let mut file = File::create(&path).await?;
+            file.write_all(&bytes).await?;
+            file.sync_all().await
+        })
+    }
+
+    fn list(&self, path: &Path) -> BoxedFuture<'static, IoResult<Vec<StorageEntry>>> {
+        let path = path.to_owned();
+        Box::pin(async move {
+            let mut entries = read_dir(&path).await?;
+            let mut list = Vec::new();
+            while let Some(entry) = entries.next().await {
+                let entry = entry?;
+                let Ok(name) = entry.file_name().into_string() else { continue };
+                list.push(StorageEntry {
+                    name,
+                    is_dir: entry.file_type().await?.is_dir(),
+                })
+            }
+
+            Ok(list)
+        })
+    }
+
+    fn delete(&self, path: &Path) -> BoxedFuture<'static, IoResult<()>> {
+        let path = path.to_owned();
+        Box::pin(async move {
+            if metadata(&path).await?.is_dir() { remove_dir_all(path).await } else { remove_file(path).await }
+        })
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> BoxedFuture<'static, IoResult<()>> {
+        Box::pin(rename(from.to_owned(), to.to_owned()))
+    }
+
+    fn exists(&self, path: &Path) -> BoxedFuture<'static, IoResult<bool>> {
+        let path = path.to_owned();
+        Box::pin(async move {
+            match metadata(path).await {
+                Ok(..) => Ok(true),
+                Err(e) if e.kind() == IoErrorKind::NotFound => Ok(false),
+                Err(e) => Err(e),
+            }
+        })
+    }
+}
+
+/// Stores files in memory, e.g. for tests. Directories only exist while they contain files. Clones
+/// share the same files.
+#[derive(Clone, Default, Debug)]
+pub struct MemoryBackend {
+    files: Arc<Mutex<BTreeMap<PathBuf, Vec<u8>>>>,
+}
+
+impl MemoryBackend {
+    /// Runs `op` on the files once the returned future is polled, like filesystem operations would.
+    fn with_files<T: Send + 'static>(
+        &self,
+        op: impl FnOnce(&mut BTreeMap<PathBuf, Vec<u8>>) -> IoResult<T> + Send + 'static,
+    ) -> BoxedFuture<'static, IoResult<T>> {
+        let files = self.files.clone();
+        Box::pin(async move { op(&mut files.lock().unwrap_or_else(PoisonError::into_inner)) })
+    }
+
+    /// Paths of every file in the directory at `path`, recursively.
+    fn files_under(files: &BTreeMap<PathBuf, Vec<u8>>, path: &Path) -> Vec<PathBuf> {
+        files
+            .keys()
+            .filter(|file| file.starts_with(path) && *file != path)
+            .cloned()
+            .collect()
+    }
+}
+
+fn not_found(path: &Path) -> IoError {
+    IoError::new(
+        IoErrorKind::NotFound,
+        format!("No such file or directory: {}", path.display()),
+    )
+}
+
+fn is_a_directory(path: &Path) -> IoError {
+    IoError::new(IoErrorKind::IsADirectory, format!("Is a directory: {}", path.display()))
+}
+
+impl StorageBackend for MemoryBackend {
+    fn read(&self, path: &Path) -> BoxedFuture<'static, IoResult<Vec<u8>>> {
+        let path = path.to_owned();
+        self.with_files(move |files| files.get(&path).cloned().ok_or_else(|| not_found(&path)))
+    }
+
+    fn write(&self, path: &Path, bytes: Vec<u8>) -> BoxedFuture<'static, IoResult<()>> {
+        let path = path.to_owned();
+        self.with_files(move |files| {
+            if !Self::files_under(files, &path).is_empty() {
+                return Err(is_a_directory(&path))
+            }
+
+            files.insert(path, bytes);
+            Ok(())
+        })
+    }
+
+    fn list(&self, path: &Path) -> BoxedFuture<'static, IoResult<Vec<StorageEntry>>> {
+        let path = path.to_owned();
+        self.with_files(move |files| {
+            let mut children = HashMap::<String, bool>::new();
+            for file in Self::files_under(files, &path) {
+                let mut components = file.strip_prefix(&path).expect("filtered by prefix").components();
+                let Some(name) = components.next().and_then(|name| name.as_os_str().to_str()) else { continue };
+                *children.entry(name.to_owned()).or_default() |= components.next().is_some();
+            }
+
+            if children.is_empty() {
+                return Err(not_found(&path))
+            }
+
+            Ok(children
+                .into_iter()
+                .map(|(name, is_dir)| StorageEntry { name, is_dir })
+                .collect())
+        })
+    }
+
+    fn delete(&self, path: &Path) -> BoxedFuture<'static, IoResult<()>> {
+        let path = path.to_owned();
+        self.with_files(move |files| {
+            let removed = files.remove(&path).is_some();
+            let under = Self::files_under(files, &path);
+            if !removed && under.is_empty() {
+                return Err(not_found(&path))
+            }
+
+            for file in under {
+                files.remove(&file);
+            }
+
+            Ok(())
+        })
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> BoxedFuture<'static, IoResult<()>> {
+        let (from, to) = (from.to_owned(), to.to_owned());
+        self.with_files(move |files| {
+            let occupied = !Self::files_under(files, &to).is_empty();
+            if files.contains_key(&from) {
+                if occupied {
+                    return Err(is_a_directory(&to))
+                }
+
+                let bytes = files.remove(&from).expect("checked above");
+                files.insert(to, bytes);
+                return Ok(())
+            }
+
+            let under = Self::files_under(files, &from);
+            if under.is_empty() {
+                return Err(not_found(&from))
+            }
+
+            // Directories only exist while they contain files, so there's no empty one to replace.
+            if occupied {
+                return Err(IoError::new(
+                    IoErrorKind::DirectoryNotEmpty,
+                    format!("Directory not empty: {}", to.display()),
+                ))
+            }
+
+            if files.contains_key(&to) {
+                return Err(IoError::new(
+                    IoErrorKind::NotADirectory,
+                    format!("Not a directory: {}", to.display()),
+                ))
+            }
+
+            for file in under {
+                let bytes = files.remove(&file).expect("listed above");
+                files.insert(to.join(file.strip_prefix(&from).expect("filtered by prefix")), bytes);
+            }
+
+            Ok(())
+        })
+    }
+
+    fn exists(&self, path: &Path) -> BoxedFuture<'static, IoResult<bool>> {
+        let path = path.to_owned();
+        self.with_files(move |files| Ok(files.contains_key(&path) || !Self::files_under(files, &path).is_empty()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::tasks::block_on;
+
+    use super::*;
+
+    #[test]
+    fn memory_directories() {
+        let backend = MemoryBackend::default();
+        block_on(async {
+            backend.write(Path::new("saves/a/data"), b"data".to_vec()).await.unwrap();
+            backend.write(Path::new("saves/a/meta"), b"meta".to_vec()).await.unwrap();
+            backend.write(Path::new("saves/top"), b"top".to_vec()).await.unwrap();
+
+            let mut list = backend.list(Path::new("saves")).await.unwrap();
+            list.sort_by(|a, b| a.name.cmp(&b.name));
+            assert_eq!(list, [
+                StorageEntry {
+                    name: "a".into(),
+                    is_dir: true
+                },
+                StorageEntry {
+                    name: "top".into(),
+                    is_dir: false
+                },
+            ]);
+
+            backend.rename(Path::new("saves/a"), Path::new("saves/b")).await.unwrap();
+            assert!(!backend.exists(Path::new("saves/a")).await.unwrap());
+            assert_eq!(backend.read(Path::new("saves/b/meta")).await.unwrap(), b"meta");
+
+            // Moves never merge into or replace a directory, nor replace a file with one.
+            backend.write(Path::new("saves/c/data"), b"other".to_vec()).await.unwrap();
+            for (from, to, kind) in [
+                ("saves/top", "saves/b", IoErrorKind::IsADirectory),
+                ("saves/b", "saves/c", IoErrorKind::DirectoryNotEmpty),
+                ("saves/b", "saves/top", IoErrorKind::NotADirectory),
+            ] {
+                let e = backend.rename(Path::new(from), Path::new(to)).await.unwrap_err();
+                assert_eq!(e.kind(), kind, "{from} -> {to}");
+            }
+            assert_eq!(backend.read(Path::new("saves/c/data")).await.unwrap(), b"other");
+            assert!(!backend.exists(Path::new("saves/c/meta")).await.unwrap());
+
+            backend
+                .rename(Path::new("saves/top"), Path::new("saves/c/data"))
+                .await
+                .unwrap();
+            assert_eq!(backend.read(Path::new("saves/c/data")).await.unwrap(), b"top");
+
+            backend.delete(Path::new("saves/b")).await.unwrap();
+            assert!(!backend.exists(Path::new("saves/b/data")).await.unwrap());
+            assert_eq!(
+                backend.list(Path::new("saves/b")).await.unwrap_err().kind(),
+                IoErrorKind::NotFound
+            );
+        })
+    }
+}
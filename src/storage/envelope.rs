@@ -0,0 +1,184 @@
+use std::{
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use bevy::{tasks::futures_lite::AsyncWrite, utils::ConditionalSendFuture};
+
+use crate::Storage;
+
+/// Version of the envelope layout itself, independent of the payload's own `Persist` versions.
+const ENVELOPE_VERSION: u16 = 0;
+/// Magic, envelope version, and payload length.
+const HEADER_LEN: usize = 4 + 2 + 4;
+/// CRC32 of the header and payload.
+const TRAILER_LEN: usize = 4;
+
+impl Storage {
+    /// Magic bytes identifying the kind of file, so a save is never decoded as a preference file.
+    pub const fn magic(&self) -> [u8; 4] {
+        match self {
+            Self::Settings => *b"CTPF",
+            Self::Saves => *b"CTSV",
+            Self::Replays => *b"CTRP",
+        }
+    }
+}
+
+/// Wraps `payload` in a magic-tagged, length-prefixed, checksummed envelope.
+pub fn seal_envelope(magic: [u8; 4], payload: &[u8]) -> IoResult<Vec<u8>> {
+    let len = u32::try_from(payload.len()).map_err(|_| {
+        IoError::new(
+            IoErrorKind::InvalidInput,
+            format!("Payload too large: {} bytes", payload.len()),
+        )
+    })?;
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len() + TRAILER_LEN);
+    bytes.extend_from_slice(&magic);
+    bytes.extend_from_slice(&ENVELOPE_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&len.to_le_bytes());
+    bytes.extend_from_slice(payload);
+
+    let checksum = crc32fast::hash(&bytes);
+    bytes.extend_from_slice(&checksum.to_le_bytes());
+    Ok(bytes)
+}
+
+/// Verifies an envelope written by [`seal_envelope`] and returns its payload, reporting truncated,
+/// foreign, or otherwise corrupt files as [`IoErrorKind::InvalidData`].
+pub fn open_envelope(magic: [u8; 4], mut bytes: Vec<u8>) -> IoResult<Vec<u8>> {
+    let corrupt = |reason: String| IoError::new(IoErrorKind::InvalidData, format!("Corrupt file: {reason}"));
+    if bytes.len() < HEADER_LEN + TRAILER_LEN {
+        return Err(corrupt(format!("truncated to {} bytes", bytes.len())))
+    }
+
+    let found = [bytes[0], bytes[1], bytes[2], bytes[3]];
+    if found != magic {
+        return Err(corrupt(format!("expected magic {magic:?}, found {found:?}")))
+    }
+
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != ENVELOPE_VERSION {
+        return Err(corrupt(format!("unsupported envelope version {version}")))
+    }
+
+    let len = u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]) as usize;
+    if bytes.len() != HEADER_LEN + len + TRAILER_LEN {
+        return Err(corrupt(format!(
+            "payload length {len} doesn't match file size of {} bytes",
+            bytes.len()
+        )))
+    }
+
+    let (content, trailer) = bytes.split_at(HEADER_LEN + len);
+    let expected = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+
+    if crc32fast::hash(content) != expected {
+        return Err(corrupt("checksum mismatch".into()))
+    }
+
+    bytes.truncate(HEADER_LEN + len);
+    bytes.drain(..HEADER_LEN);
+    Ok(bytes)
+}
+
+/// Buffers the payload in memory and only hands the sealed envelope to `commit` once closed, so
+/// nothing reaches the file unless the whole payload was successfully serialized.
+pub struct EnvelopeWriter<F, Fut>
+where
+    F: FnOnce(Vec<u8>) -> Fut,
+    Fut: ConditionalSendFuture<Output = IoResult<()>>,
+{
+    magic: [u8; 4],
+    payload: Vec<u8>,
+    commit: Option<F>,
+    committing: Option<Pin<Box<Fut>>>,
+}
+
+impl<F, Fut> EnvelopeWriter<F, Fut>
+where
+    F: FnOnce(Vec<u8>) -> Fut,
+    Fut: ConditionalSendFuture<Output = IoResult<()>>,
+{
+    pub fn new(magic: [u8; 4], commit: F) -> Self {
+        Self {
+            magic,
+            payload: Vec::new(),
+            commit: Some(commit),
+            committing: None,
+        }
+    }
+}
+
+impl<F, Fut> AsyncWrite for EnvelopeWriter<F, Fut>
+where
+    F: FnOnce(Vec<u8>) -> Fut + Unpin,
+    Fut: ConditionalSendFuture<Output = IoResult<()>>,
+{
+    fn poll_write(self: Pin<&mut Self>, _: &mut Context<'_>, buf: &[u8]) -> Poll<IoResult<usize>> {
+        let this = self.get_mut();
+        if this.commit.is_none() {
+            return Poll::Ready(Err(IoError::other("Envelope already closed")))
+        }
+
+        this.payload.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<IoResult<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<IoResult<()>> {
+        let this = self.get_mut();
+        if let Some(commit) = this.commit.take() {
+            let bytes = seal_envelope(this.magic, &std::mem::take(&mut this.payload))?;
+            this.committing = Some(Box::pin(commit(bytes)));
+        }
+
+        match &mut this.committing {
+            Some(committing) => {
+                let result = ready!(committing.as_mut().poll(cx));
+                this.committing = None;
+                Poll::Ready(result)
+            }
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let magic = Storage::Settings.magic();
+        let bytes = seal_envelope(magic, b"payload").unwrap();
+        assert_eq!(open_envelope(magic, bytes).unwrap(), b"payload");
+    }
+
+    #[test]
+    fn rejects_corruption() {
+        let magic = Storage::Settings.magic();
+        let bytes = seal_envelope(magic, b"payload").unwrap();
+
+        for len in 0..bytes.len() {
+            let e = open_envelope(magic, bytes[..len].to_vec()).unwrap_err();
+            assert_eq!(e.kind(), IoErrorKind::InvalidData);
+        }
+
+        for i in 0..bytes.len() {
+            let mut flipped = bytes.clone();
+            flipped[i] ^= 1;
+
+            let e = open_envelope(magic, flipped).unwrap_err();
+            assert_eq!(e.kind(), IoErrorKind::InvalidData);
+        }
+
+        let e = open_envelope(Storage::Saves.magic(), bytes).unwrap_err();
+        assert_eq!(e.kind(), IoErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    io::{ErrorKind as IoErrorKind, Result as IoResult},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy::{
+    prelude::*,
+    tasks::futures_lite::{io::Cursor, AsyncRead, AsyncWrite},
+    utils::{ConditionalSend, ConditionalSendFuture},
+};
+
+use crate::persist::{Persist, PersistMigrate, PersistReader, PersistWriter};
+
+mod atomic;
+mod backend;
+mod envelope;
+mod path;
+mod queue;
+mod root;
+mod setting;
+mod slot;
+pub use atomic::*;
+pub use backend::*;
+pub use envelope::*;
+pub use path::*;
+pub use queue::*;
+pub use root::*;
+pub use setting::*;
+pub use slot::*;
+
+pub enum Storage {
+    Settings,
+    Saves,
+    Replays,
+}
+
+#[derive(Resource, Debug)]
+pub struct LocalStorage {
+    settings_dir: PathBuf,
+    saves_dir: PathBuf,
+    replays_dir: PathBuf,
+    backend: Arc<dyn StorageBackend>,
+    writes: WriteQueue,
+}
+
+impl LocalStorage {
+    fn dir(&self, storage: Storage) -> &Path {
+        match storage {
+            Storage::Settings => &self.settings_dir,
+            Storage::Saves => &self.saves_dir,
+            Storage::Replays => &self.replays_dir,
+        }
+    }
+
+    /// Reads the payload of `file`, see [`read_with_backup`]. Files from before envelopes were
+    /// introduced are read as a bare payload and queued to be rewritten in an envelope.
+    pub fn reader(
+        &self,
+        storage: Storage,
+        file: StoragePath,
+    ) -> impl ConditionalSendFuture<Output = IoResult<PersistReader<impl AsyncRead + ConditionalSend + use<>>>> + use<> {
+        let magic = storage.magic();
+        let path = self.dir(storage).join(file);
+
+        let (backend, writes) = (self.backend.clone(), self.writes.clone());
+        async move {
+            let payload = match read_with_backup(backend.clone(), path.clone(), magic).await {
+                Ok(payload) => payload,
+                Err(e) if e.kind() == IoErrorKind::InvalidData => match backend.read(&path).await {
+                    Ok(legacy) if !legacy.starts_with(&magic) => {
+                        warn!("Upgrading {} to an envelope", path.display());
+                        writes.write(&backend, path, seal_envelope(magic, &legacy)?);
+                        legacy
+                    }
+                    _ => return Err(e),
+                },
+                Err(e) => return Err(e),
+            };
+
+            Ok(PersistReader::new(Cursor::new(payload)))
+        }
+    }
+
+    pub fn writer(
+        &self,
+        storage: Storage,
+        file: StoragePath,
+    ) -> impl ConditionalSendFuture<Output = IoResult<PersistWriter<impl AsyncWrite + ConditionalSend + use<>>>> + use<> {
+        let magic = storage.magic();
+        let path = self.dir(storage).join(file);
+
+        let (backend, writes) = (self.backend.clone(), self.writes.clone());
+        async move {
+            Ok(PersistWriter::new(EnvelopeWriter::new(magic, move |bytes| {
+                let write = writes.write(&backend, path, bytes);
+                async move { write.wait().await.map(|_| ()) }
+            })))
+        }
+    }
+
+    /// Writes made through [`writer`](Self::writer), which are queued per file and resolve once on disk.
+    pub fn writes(&self) -> &WriteQueue {
+        &self.writes
+    }
+}
+
+impl Default for LocalStorage {
+    fn default() -> Self {
+        Self::from_root(&StorageRoot::System)
+    }
+}
+
+#[derive(Persist, Resource, Copy, Clone, Debug)]
+#[persist(version = 1, auto, migrate)]
+pub struct InputKeyboardPref {
+    /// Up-down-left-right, defaults to WSAD.
+    pub movement: [KeyCode; 4],
+    /// Defaults to H.
+    pub primary: KeyCode,
+    /// Defaults to J.
+    pub secondary: KeyCode,
+    /// Defaults to space.
+    pub jump: KeyCode,
+    /// Defaults to left shift.
+    pub dash: KeyCode,
+}
+
+impl Default for InputKeyboardPref {
+    fn default() -> Self {
+        Self {
+            movement: [KeyCode::KeyW, KeyCode::KeyS, KeyCode::KeyA, KeyCode::KeyD],
+            primary: KeyCode::KeyH,
+            secondary: KeyCode::KeyJ,
+            jump: KeyCode::Space,
+            dash: KeyCode::ShiftLeft,
+        }
+    }
+}
+
+/// [`InputKeyboardPref`] as of version 0, before attacks, jumping and dashing were rebindable.
+#[derive(Persist, Copy, Clone, Debug)]
+#[persist(auto)]
+pub struct InputKeyboardPrefV0 {
+    pub movement: [KeyCode; 4],
+}
+
+impl PersistMigrate<0> for InputKeyboardPref {
+    type Legacy = InputKeyboardPrefV0;
+    type Next = Self;
+
+    fn migrate(legacy: Self::Legacy) -> Self::Next {
+        Self {
+            movement: legacy.movement,
+            ..default()
+        }
+    }
+}
+
+#[derive(Persist, Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[persist(auto)]
+pub enum Stick {
+    #[default]
+    #[persist(tag = 0)]
+    Left,
+    #[persist(tag = 1)]
+    Right,
+}
+
+#[derive(Persist, Resource, Copy, Clone, Debug)]
+#[persist(version = 0, auto)]
+pub struct InputGamepadPref {
+    /// Defaults to the left stick.
+    pub movement: Stick,
+    /// Defaults to West (PS: Square, Xbox: X).
+    pub primary: GamepadButton,
+    /// Defaults to North (PS: Triangle, Xbox: Y).
+    pub secondary: GamepadButton,
+    /// Defaults to South (PS: Cross, Xbox: A).
+    pub jump: GamepadButton,
+    /// Defaults to East (PS: Circle, Xbox: B).
+    pub dash: GamepadButton,
+}
+
+impl Default for InputGamepadPref {
+    fn default() -> Self {
+        Self {
+            movement: Stick::Left,
+            primary: GamepadButton::West,
+            secondary: GamepadButton::North,
+            jump: GamepadButton::South,
+            dash: GamepadButton::East,
+        }
+    }
+}
+
+pub struct StoragePlugin {
+    pub root: StorageRoot,
+    /// Where files are actually kept, the filesystem by default.
+    pub backend: Arc<dyn StorageBackend>,
+}
+
+impl StoragePlugin {
+    /// Keeps everything in a fresh [`MemoryBackend`], so nothing touches the disk.
+    pub fn in_memory() -> Self {
+        Self {
+            root: default(),
+            backend: Arc::new(MemoryBackend::default()),
+        }
+    }
+}
+
+impl Default for StoragePlugin {
+    fn default() -> Self {
+        Self {
+            root: default(),
+            backend: Arc::new(FileBackend),
+        }
+    }
+}
+
+impl Plugin for StoragePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(LocalStorage::from_root(&self.root).with_backend(self.backend.clone()))
+            .register_setting::<InputKeyboardPref>("keyboard.pref")
+            .register_setting::<InputGamepadPref>("gamepad.pref")
+            .init_resource::<SaveSlots>()
+            .add_event::<SlotRequest>()
+            .add_event::<SlotResponse>()
+            .add_systems(Startup, refresh_slots)
+            .add_systems(Update, handle_slot_requests)
+            .add_systems(Last, wait_for_writes.run_if(on_event::<AppExit>));
+    }
+}
+
+/// Blocks until every queued write is on disk before the app exits.
+#[cfg(not(target_arch = "wasm32"))]
+fn wait_for_writes(storage: Res<LocalStorage>) {
+    storage.writes.wait_idle()
+}
+
+/// The main thread can't block on the web, but queued writes keep running on the page's event loop
+/// after the app exits, so they still finish unless the page is closed first.
+#[cfg(target_arch = "wasm32")]
+fn wait_for_writes(storage: Res<LocalStorage>) {
+    if !storage.writes.is_idle() {
+        warn!("Exiting with writes in progress, closing the page now may lose them")
+    }
+}
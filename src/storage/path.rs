@@ -0,0 +1,225 @@
+use std::{
+    error::Error,
+    fmt::{Display, Formatter, Result as FmtResult},
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+    path::{Component, Path, PathBuf},
+};
+
+/// Names Windows reserves for devices, regardless of case or extension. Superscript digits count as
+/// digits there too.
+const RESERVED_NAMES: [&str; 30] = [
+    "CON", "PRN", "AUX", "NUL", "COM0", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "COM¹",
+    "COM²", "COM³", "LPT0", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9", "LPT¹", "LPT²", "LPT³",
+];
+
+/// Characters not allowed in file names on at least one supported platform.
+const INVALID_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Why a path was rejected by [`StoragePath`].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum StoragePathError {
+    Empty,
+    /// Absolute, or starting at a root or drive.
+    NotRelative,
+    /// Contains `.` or `..`.
+    Traversal,
+    /// Has several components where a single name was expected.
+    Nested,
+    NotUnicode,
+    /// A component longer than [`StoragePath::MAX_NAME_LEN`] characters or
+    /// [`StoragePath::MAX_NAME_BYTES`] bytes.
+    TooLong(String),
+    /// A device name reserved on Windows, such as `CON` or `nul.txt`.
+    Reserved(String),
+    /// A component containing a control character or one of `<>:"/\|?*`, or ending with a dot or a
+    /// space.
+    InvalidChar(String, char),
+}
+
+impl Display for StoragePathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::Empty => write!(f, "Storage path is empty"),
+            Self::NotRelative => write!(f, "Storage path must be relative"),
+            Self::Traversal => write!(f, "Storage path must not contain `.` or `..`"),
+            Self::Nested => write!(f, "Expected a single name, not a path"),
+            Self::NotUnicode => write!(f, "Storage path must be valid Unicode"),
+            Self::TooLong(name) => write!(
+                f,
+                "`{name}` is longer than {} characters or {} bytes",
+                StoragePath::MAX_NAME_LEN,
+                StoragePath::MAX_NAME_BYTES
+            ),
+            Self::Reserved(name) => write!(f, "`{name}` is a reserved name"),
+            Self::InvalidChar(name, c) => write!(f, "`{name}` contains or ends with invalid character {c:?}"),
+        }
+    }
+}
+
+impl Error for StoragePathError {}
+
+/// Paths that try to leave their directory are [`IoErrorKind::InvalidInput`], and bad names are
+/// [`IoErrorKind::InvalidFilename`]. The [`StoragePathError`] is kept as the inner error.
+impl From<StoragePathError> for IoError {
+    fn from(e: StoragePathError) -> Self {
+        let kind = match e {
+            StoragePathError::Empty |
+            StoragePathError::NotRelative |
+            StoragePathError::Traversal |
+            StoragePathError::Nested => IoErrorKind::InvalidInput,
+            StoragePathError::NotUnicode |
+            StoragePathError::TooLong(..) |
+            StoragePathError::Reserved(..) |
+            StoragePathError::InvalidChar(..) => IoErrorKind::InvalidFilename,
+        };
+
+        Self::new(kind, e)
+    }
+}
+
+/// A relative path that stays within whichever [`LocalStorage`](crate::LocalStorage) directory it's
+/// resolved against, made only of names that are valid on every supported platform.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct StoragePath(PathBuf);
+
+impl StoragePath {
+    /// Longest name, in characters, of each component, so names in any script get the same room.
+    pub const MAX_NAME_LEN: usize = 64;
+    /// Longest name, in UTF-8 bytes, of each component. Common filesystems allow 255, which leaves
+    /// room for the `.tmp` and `.bak` suffixes of temporary and backup files.
+    pub const MAX_NAME_BYTES: usize = 250;
+
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, StoragePathError> {
+        let path = path.as_ref();
+        let mut empty = true;
+        for component in path.components() {
+            match component {
+                Component::Prefix(..) | Component::RootDir => return Err(StoragePathError::NotRelative),
+                Component::CurDir | Component::ParentDir => return Err(StoragePathError::Traversal),
+                Component::Normal(name) => validate_name(name.to_str().ok_or(StoragePathError::NotUnicode)?)?,
+            }
+
+            empty = false
+        }
+
+        if empty {
+            return Err(StoragePathError::Empty)
+        }
+
+        Ok(Self(path.to_owned()))
+    }
+
+    /// A path made of `name` alone, e.g. a save slot, rejecting any separators in it.
+    pub fn name(name: &str) -> Result<Self, StoragePathError> {
+        let path = Self::new(name)?;
+        if path.0.components().nth(1).is_some() {
+            return Err(StoragePathError::Nested)
+        }
+
+        Ok(path)
+    }
+
+    /// Appends `name`, which must itself be a valid single name.
+    pub fn join(&self, name: &str) -> Result<Self, StoragePathError> {
+        Ok(Self(self.0.join(Self::name(name)?.0)))
+    }
+
+    pub fn as_path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for StoragePath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl TryFrom<&str> for StoragePath {
+    type Error = StoragePathError;
+
+    fn try_from(path: &str) -> Result<Self, Self::Error> {
+        Self::new(path)
+    }
+}
+
+fn validate_name(name: &str) -> Result<(), StoragePathError> {
+    if name.len() > StoragePath::MAX_NAME_BYTES || name.chars().count() > StoragePath::MAX_NAME_LEN {
+        return Err(StoragePathError::TooLong(name.into()))
+    }
+
+    if let Some(c) = name.chars().find(|&c| c.is_control() || INVALID_CHARS.contains(&c)) {
+        return Err(StoragePathError::InvalidChar(name.into(), c))
+    }
+
+    // Windows silently strips these, so `slot.` and `slot` would be the same file.
+    if let Some(c) = name.chars().next_back().filter(|&c| c == '.' || c == ' ') {
+        return Err(StoragePathError::InvalidChar(name.into(), c))
+    }
+
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        return Err(StoragePathError::Reserved(name.into()))
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates() {
+        assert!(StoragePath::new("keyboard.pref").is_ok());
+        assert!(StoragePath::new("slot/data.save").is_ok());
+        assert!(StoragePath::name("My save 1").is_ok());
+
+        assert_eq!(StoragePath::new(""), Err(StoragePathError::Empty));
+        assert_eq!(StoragePath::new("/etc/passwd"), Err(StoragePathError::NotRelative));
+        assert_eq!(StoragePath::new("../../etc/x"), Err(StoragePathError::Traversal));
+        assert_eq!(StoragePath::new("slot/../../x"), Err(StoragePathError::Traversal));
+        assert_eq!(StoragePath::name("slot/data.save"), Err(StoragePathError::Nested));
+        assert!(matches!(StoragePath::name("a|b"), Err(StoragePathError::InvalidChar(_, '|'))));
+        assert!(matches!(
+            StoragePath::name("ab:c"),
+            Err(StoragePathError::InvalidChar(_, ':'))
+        ));
+        assert!(matches!(
+            StoragePath::name("slot."),
+            Err(StoragePathError::InvalidChar(_, '.'))
+        ));
+        assert!(matches!(StoragePath::name("nul.txt"), Err(StoragePathError::Reserved(..))));
+        assert!(matches!(StoragePath::name("Com1"), Err(StoragePathError::Reserved(..))));
+        assert!(matches!(
+            StoragePath::name(&"a".repeat(65)),
+            Err(StoragePathError::TooLong(..))
+        ));
+        assert!(matches!(StoragePath::name("com0.txt"), Err(StoragePathError::Reserved(..))));
+        assert!(matches!(StoragePath::name("LPT³"), Err(StoragePathError::Reserved(..))));
+        assert!(StoragePath::name("COM10").is_ok());
+
+        // Limits count characters, so scripts with multi-byte characters get the same room.
+        assert!(StoragePath::name("Sauvegarde n°1 — forêt").is_ok());
+        assert!(StoragePath::name(&"セ".repeat(64)).is_ok());
+        assert!(matches!(
+            StoragePath::name(&"セ".repeat(65)),
+            Err(StoragePathError::TooLong(..))
+        ));
+        // Except where that would exceed the byte limit.
+        assert!(matches!(
+            StoragePath::name(&"😀".repeat(63)),
+            Err(StoragePathError::TooLong(..))
+        ));
+        assert!(matches!(
+            StoragePath::name("セーブ?"),
+            Err(StoragePathError::InvalidChar(_, '?'))
+        ));
+
+        assert_eq!(IoError::from(StoragePathError::Traversal).kind(), IoErrorKind::InvalidInput);
+        assert_eq!(
+            IoError::from(StoragePathError::Reserved("CON".into())).kind(),
+            IoErrorKind::InvalidFilename
+        );
+    }
+}
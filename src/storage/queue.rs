@@ -0,0 +1,212 @@
+use std::{
+    collections::HashMap,
+    io::{Error as IoError, Result as IoResult},
+    path::{Path, PathBuf},
+    sync::{Arc, Condvar, Mutex, PoisonError},
+};
+
+use async_channel::{Receiver, Sender};
+use bevy::tasks::IoTaskPool;
+
+use crate::{StorageBackend, write_atomic};
+
+/// What became of a write queued with [`WriteQueue::write`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum WriteOutcome {
+    /// The contents are durably on disk.
+    Written,
+    /// A newer write to the same file was queued before this one started, so it was dropped.
+    Superseded,
+}
+
+type Pending = Option<(Vec<u8>, Sender<IoResult<WriteOutcome>>)>;
+
+#[derive(Default, Debug)]
+struct Files {
+    /// Files with a write in progress, and the write to do after it, if any.
+    pending: HashMap<PathBuf, Pending>,
+    /// Dropped whenever a file is done being written, waking up [`WriteQueue::wait_for`]s.
+    waiters: Vec<Sender<()>>,
+}
+
+/// Serializes [`write_atomic`]s to the same file, so they land in the order they were queued. While
+/// a file is being written, only the latest write queued after it is kept; older ones are
+/// [superseded](WriteOutcome::Superseded), since they'd be overwritten right away anyway.
+#[derive(Clone, Default, Debug)]
+pub struct WriteQueue {
+    files: Arc<Mutex<Files>>,
+    /// Notified whenever a file is done being written.
+    done: Arc<Condvar>,
+}
+
+impl WriteQueue {
+    /// Queues `bytes` to be written to `path` through `backend` in the background. The write
+    /// happens even if the returned handle is dropped.
+    pub fn write(&self, backend: &Arc<dyn StorageBackend>, path: PathBuf, bytes: Vec<u8>) -> WriteHandle {
+        let (sender, receiver) = async_channel::bounded(1);
+
+        let mut files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        match files.pending.get_mut(&path) {
+            Some(pending) => {
+                if let Some((.., superseded)) = pending.replace((bytes, sender)) {
+                    superseded.try_send(Ok(WriteOutcome::Superseded)).ok();
+                }
+            }
+            None => {
+                files.pending.insert(path.clone(), None);
+
+                let files = self.files.clone();
+                let done = self.done.clone();
+                let backend = backend.clone();
+                IoTaskPool::get()
+                    .spawn(async move {
+                        let (mut bytes, mut sender) = (bytes, sender);
+                        loop {
+                            let result = write_atomic(backend.clone(), path.clone(), bytes).await;
+                            sender.try_send(result.map(|()| WriteOutcome::Written)).ok();
+
+                            let mut files = files.lock().unwrap_or_else(PoisonError::into_inner);
+                            match files.pending.get_mut(&path).and_then(Option::take) {
+                                Some(next) => (bytes, sender) = next,
+                                None => {
+                                    files.pending.remove(&path);
+                                    files.waiters.clear();
+                                    done.notify_all();
+                                    break
+                                }
+                            }
+                        }
+                    })
+                    .detach()
+            }
+        }
+
+        WriteHandle(receiver)
+    }
+
+    /// Whether no file has a write in progress or queued.
+    pub fn is_idle(&self) -> bool {
+        self.files.lock().unwrap_or_else(PoisonError::into_inner).pending.is_empty()
+    }
+
+    /// Waits until no write to `path` or any file under it is in progress or queued, e.g. before
+    /// deleting a directory that a queued write would otherwise bring back.
+    pub async fn wait_for(&self, path: &Path) {
+        loop {
+            let waiter = {
+                let mut files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+                if !files.pending.keys().any(|file| file.starts_with(path)) {
+                    break
+                }
+
+                let (sender, receiver) = async_channel::bounded::<()>(1);
+                files.waiters.push(sender);
+                receiver
+            };
+
+            // Fails once the sender is dropped, which is all this waits for.
+            waiter.recv().await.ok();
+        }
+    }
+
+    /// Blocks until every write, including ones queued meanwhile, is done, e.g. before the app
+    /// exits. Not available on the web, where the main thread can't block.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait_idle(&self) {
+        let files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        drop(
+            self.done
+                .wait_while(files, |files| !files.pending.is_empty())
+                .unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+}
+
+/// Reports the [`WriteOutcome`] of a write queued with [`WriteQueue::write`].
+#[derive(Debug)]
+pub struct WriteHandle(Receiver<IoResult<WriteOutcome>>);
+
+impl WriteHandle {
+    /// Waits until the write is either on disk, superseded, or failed.
+    pub async fn wait(self) -> IoResult<WriteOutcome> {
+        self.0
+            .recv()
+            .await
+            .unwrap_or_else(|_| Err(IoError::other("Write queue dropped the write")))
+    }
+
+    /// Takes the outcome if the write is already done, for polling from systems.
+    pub fn try_outcome(&self) -> Option<IoResult<WriteOutcome>> {
+        self.0.try_recv().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, process};
+
+    use async_fs::{read, remove_dir_all};
+    use bevy::tasks::{TaskPool, block_on};
+
+    use super::*;
+    use crate::FileBackend;
+
+    #[test]
+    fn serializes_writes() {
+        IoTaskPool::get_or_init(TaskPool::default);
+        let dir = temp_dir().join(format!("centripetal-queue-{}", process::id()));
+        let path = dir.join("test.pref");
+        let queue = WriteQueue::default();
+        let backend: Arc<dyn StorageBackend> = Arc::new(FileBackend);
+
+        let handles = [b"first", b"secnd", b"third"].map(|bytes| queue.write(&backend, path.clone(), bytes.to_vec()));
+        block_on(async {
+            let [first, second, third] = handles;
+            assert_eq!(first.wait().await.unwrap(), WriteOutcome::Written);
+            // Depending on timing, the second write either made it or was replaced by the third.
+            second.wait().await.unwrap();
+            assert_eq!(third.wait().await.unwrap(), WriteOutcome::Written);
+
+            assert_eq!(read(&path).await.unwrap(), b"third");
+            remove_dir_all(dir).await.unwrap();
+        })
+    }
+
+    #[test]
+    fn waits_until_idle() {
+        IoTaskPool::get_or_init(TaskPool::default);
+        let dir = temp_dir().join(format!("centripetal-queue-idle-{}", process::id()));
+        let queue = WriteQueue::default();
+        let backend: Arc<dyn StorageBackend> = Arc::new(FileBackend);
+
+        // Nobody waits on the handles, like writes still in flight when the app exits.
+        for file in ["a.pref", "b.pref"] {
+            drop(queue.write(&backend, dir.join(file), file.as_bytes().to_vec()))
+        }
+
+        queue.wait_idle();
+        assert!(queue.is_idle());
+        block_on(async {
+            assert_eq!(read(dir.join("a.pref")).await.unwrap(), b"a.pref");
+            assert_eq!(read(dir.join("b.pref")).await.unwrap(), b"b.pref");
+            remove_dir_all(dir).await.unwrap();
+        })
+    }
+
+    #[test]
+    fn supersedes_pending_writes() {
+        IoTaskPool::get_or_init(TaskPool::default);
+        let path = temp_dir().join(format!("centripetal-queue-pending-{}", process::id()));
+        let queue = WriteQueue::default();
+        let backend: Arc<dyn StorageBackend> = Arc::new(FileBackend);
+
+        // Pretend a write is already in progress, so nothing starts writing.
+        queue.files.lock().unwrap().pending.insert(path.clone(), None);
+
+        let older = queue.write(&backend, path.clone(), b"older".to_vec());
+        let newer = queue.write(&backend, path.clone(), b"newer".to_vec());
+        assert_eq!(older.try_outcome().unwrap().unwrap(), WriteOutcome::Superseded);
+        assert!(newer.try_outcome().is_none());
+        assert!(!queue.is_idle());
+    }
+}
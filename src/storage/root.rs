@@ -0,0 +1,170 @@
+use std::{
+    env,
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use bevy::prelude::*;
+use directories::ProjectDirs;
+
+use crate::{FileBackend, LocalStorage, StorageBackend};
+
+/// Environment variable overriding the data directory, like `--data-dir`.
+pub const DATA_DIR_VAR: &str = "CENTRIPETAL_DATA_DIR";
+/// File next to the executable that enables [`StorageRoot::Portable`] without any flags.
+pub const PORTABLE_MARKER: &str = "portable";
+
+/// Where [`LocalStorage`] keeps settings, saves, and replays.
+#[derive(Clone, Debug, Default)]
+pub enum StorageRoot {
+    /// The platform's per-user directories.
+    #[default]
+    System,
+    /// A `data` directory next to the executable.
+    Portable,
+    /// An explicit directory, e.g. a temporary one for tests.
+    Dir(PathBuf),
+}
+
+impl StorageRoot {
+    /// Resolves the root from the process' command line and environment, in order of priority:
+    /// `--data-dir <dir>`, `--portable`, [`DATA_DIR_VAR`], and finally [`PORTABLE_MARKER`].
+    pub fn from_env() -> IoResult<Self> {
+        Self::from_args(env::args().skip(1), env::var_os(DATA_DIR_VAR).map(PathBuf::from))
+    }
+
+    /// Like [`from_env`](Self::from_env), but from the given arguments and [`DATA_DIR_VAR`] value.
+    /// Fails if `--data-dir` is given without a directory, rather than silently using another root.
+    pub fn from_args(args: impl IntoIterator<Item = String>, var: Option<PathBuf>) -> IoResult<Self> {
+        Self::resolve(args, var, exe_dir())
+    }
+
+    fn resolve(args: impl IntoIterator<Item = String>, var: Option<PathBuf>, exe_dir: Option<PathBuf>) -> IoResult<Self> {
+        let mut portable = false;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let dir = match arg.as_str() {
+                "--data-dir" => args.next(),
+                "--portable" => {
+                    portable = true;
+                    continue
+                }
+                arg if let Some(dir) = arg.strip_prefix("--data-dir=") => Some(dir.into()),
+                _ => continue,
+            };
+
+            return match dir {
+                Some(dir) if !dir.is_empty() => Ok(Self::Dir(dir.into())),
+                _ => Err(IoError::new(IoErrorKind::InvalidInput, "`--data-dir` needs a directory")),
+            }
+        }
+
+        Ok(if portable {
+            Self::Portable
+        } else if let Some(dir) = var {
+            Self::Dir(dir)
+        } else if exe_dir.is_some_and(|dir| dir.join(PORTABLE_MARKER).is_file()) {
+            Self::Portable
+        } else {
+            Self::System
+        })
+    }
+}
+
+fn exe_dir() -> Option<PathBuf> {
+    env::current_exe().ok()?.parent().map(Path::to_path_buf)
+}
+
+impl LocalStorage {
+    pub fn new(settings_dir: impl Into<PathBuf>, saves_dir: impl Into<PathBuf>, replays_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            settings_dir: settings_dir.into(),
+            saves_dir: saves_dir.into(),
+            replays_dir: replays_dir.into(),
+            backend: Arc::new(FileBackend),
+            writes: default(),
+        }
+    }
+
+    /// Keeps files in `backend` instead of the filesystem.
+    pub fn with_backend(mut self, backend: Arc<dyn StorageBackend>) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    /// Keeps settings, saves, and replays in `settings`, `saves`, and `replays` under `root`.
+    pub fn at(root: impl AsRef<Path>) -> Self {
+        let root = root.as_ref();
+        Self::new(root.join("settings"), root.join("saves"), root.join("replays"))
+    }
+
+    /// Resolves `root`, falling back to a portable directory, and then to the working directory, if
+    /// the preferred one can't be determined on this platform.
+    pub fn from_root(root: &StorageRoot) -> Self {
+        match root {
+            StorageRoot::System => match ProjectDirs::from("com.github", "gygl", "Centripetal") {
+                Some(dirs) => Self::new(
+                    dirs.preference_dir(),
+                    dirs.data_dir().join("saves"),
+                    dirs.data_dir().join("replays"),
+                ),
+                None => {
+                    warn!("Couldn't get project data directories, falling back to portable storage");
+                    Self::from_root(&StorageRoot::Portable)
+                }
+            },
+            StorageRoot::Portable => match exe_dir() {
+                Some(dir) => Self::at(dir.join("data")),
+                None => {
+                    warn!("Couldn't locate the executable, falling back to the working directory");
+                    Self::at("data")
+                }
+            },
+            StorageRoot::Dir(dir) => Self::at(dir),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs, process};
+
+    use super::*;
+
+    fn parse(args: &[&str]) -> IoResult<StorageRoot> {
+        StorageRoot::from_args(args.iter().map(|&arg| arg.into()), Some("var".into()))
+    }
+
+    #[test]
+    fn from_args() {
+        assert!(matches!(parse(&["--data-dir", "a"]), Ok(StorageRoot::Dir(dir)) if dir == Path::new("a")));
+        assert!(matches!(parse(&["--data-dir=b"]), Ok(StorageRoot::Dir(dir)) if dir == Path::new("b")));
+        assert!(matches!(parse(&["--portable"]), Ok(StorageRoot::Portable)));
+        assert!(matches!(parse(&[]), Ok(StorageRoot::Dir(dir)) if dir == Path::new("var")));
+
+        for args in [&["--portable", "--data-dir"][..], &["--data-dir="]] {
+            assert_eq!(parse(args).unwrap_err().kind(), IoErrorKind::InvalidInput, "{args:?}");
+        }
+    }
+
+    #[test]
+    fn portable_marker() {
+        let dir = temp_dir().join(format!("centripetal-root-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let resolve = || StorageRoot::resolve(Vec::new(), None, Some(dir.clone())).unwrap();
+        assert!(matches!(resolve(), StorageRoot::System));
+
+        fs::write(dir.join(PORTABLE_MARKER), []).unwrap();
+        assert!(matches!(resolve(), StorageRoot::Portable));
+        assert!(
+            matches!(
+                StorageRoot::resolve(Vec::new(), Some("var".into()), Some(dir.clone())).unwrap(),
+                StorageRoot::Dir(..)
+            ),
+            "the environment variable takes priority"
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
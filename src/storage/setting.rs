@@ -0,0 +1,350 @@
+use std::{
+    io::{ErrorKind as IoErrorKind, Result as IoResult},
+    pin::pin,
+    time::Duration,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::block_on;
+use bevy::{
+    prelude::*,
+    tasks::{IoTaskPool, Task},
+    utils::{ConditionalSendFuture, futures::check_ready},
+};
+
+use crate::{LocalStorage, Storage, StoragePath, persist::Persist, r, w};
+
+impl LocalStorage {
+    pub fn read_setting<T: Persist>(&self, file: &str) -> impl ConditionalSendFuture<Output = IoResult<T>> + use<T> {
+        let reader = StoragePath::new(file).map(|file| self.reader(Storage::Settings, file));
+        async move {
+            let mut r = pin!(reader?.await?);
+            r!(r, T)
+        }
+    }
+
+    pub fn write_setting<T: Persist>(
+        &self,
+        file: &str,
+        value: T,
+    ) -> impl ConditionalSendFuture<Output = IoResult<()>> + use<T> {
+        let writer = StoragePath::new(file).map(|file| self.writer(Storage::Settings, file));
+        async move {
+            let mut w = pin!(writer?.await?);
+            w!(w, T: value)?;
+            w.close().await
+        }
+    }
+}
+
+/// Tracks the file a setting registered with [`RegisterSetting::register_setting`] lives in.
+#[derive(Resource)]
+pub struct SettingFile<T: Persist + Resource> {
+    /// How long the setting has to stay unchanged before it's written, so a burst of edits (e.g.
+    /// dragging a slider) is written once. Pending changes are written right away on [`AppExit`].
+    pub debounce: Duration,
+    file: &'static str,
+    load: Option<Task<IoResult<T>>>,
+    loaded: bool,
+    /// Whether the setting was just loaded, so the change that made doesn't get written back.
+    fresh: bool,
+    /// Time since the last change that hasn't been written yet, if any.
+    pending: Option<Duration>,
+    /// Write in progress, if any. Later changes stay pending until it's done.
+    save: Option<Task<()>>,
+}
+
+impl<T: Persist + Resource> SettingFile<T> {
+    pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(500);
+
+    pub fn file(&self) -> &'static str {
+        self.file
+    }
+
+    /// Whether the setting was read from its file, or fell back to its default if that failed.
+    pub fn is_loaded(&self) -> bool {
+        self.loaded
+    }
+
+    /// Whether there are changes waiting for the debounce to pass before being written.
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Whether a write is in progress.
+    pub fn is_saving(&self) -> bool {
+        self.save.is_some()
+    }
+}
+
+pub trait RegisterSetting {
+    /// Adds `T` as a resource, initially [`Default`], that is loaded from `file` in the settings
+    /// directory on startup and written back there shortly after it changes, see
+    /// [`SettingFile::debounce`].
+    fn register_setting<T: Persist + Resource + Default>(&mut self, file: &'static str) -> &mut Self;
+}
+
+impl RegisterSetting for App {
+    fn register_setting<T: Persist + Resource + Default>(&mut self, file: &'static str) -> &mut Self {
+        self.init_resource::<T>()
+            .insert_resource(SettingFile::<T> {
+                debounce: SettingFile::<T>::DEFAULT_DEBOUNCE,
+                file,
+                load: None,
+                loaded: false,
+                fresh: false,
+                pending: None,
+                save: None,
+            })
+            .add_systems(
+                Update,
+                load_setting::<T>.run_if(|setting: Res<SettingFile<T>>| !setting.loaded),
+            )
+            // Late in the frame, so changes made anywhere in it are seen before flushing on exit.
+            .add_systems(
+                Last,
+                (save_setting::<T>, flush_setting::<T>.run_if(on_event::<AppExit>))
+                    .chain()
+                    .run_if(|setting: Res<SettingFile<T>>| setting.loaded),
+            )
+    }
+}
+
+fn load_setting<T: Persist + Resource>(
+    storage: Res<LocalStorage>,
+    mut setting: ResMut<SettingFile<T>>,
+    mut value: ResMut<T>,
+) {
+    let file = setting.file;
+    let task = setting
+        .load
+        .get_or_insert_with(|| IoTaskPool::get().spawn(storage.read_setting(file)));
+
+    if task.is_finished() {
+        let result = check_ready(task).expect("`is_finished()` implies Poll::Ready");
+        match result {
+            Ok(loaded) => *value = loaded,
+            Err(e) if e.kind() == IoErrorKind::NotFound => {}
+            Err(e) => error!("Couldn't read setting file `{file}`: {e}"),
+        }
+
+        setting.load = None;
+        setting.loaded = true;
+        setting.fresh = true
+    }
+}
+
+fn save_setting<T: Persist + Resource>(
+    time: Res<Time>,
+    storage: Res<LocalStorage>,
+    mut setting: ResMut<SettingFile<T>>,
+    value: Res<T>,
+) {
+    let fresh = std::mem::take(&mut setting.fresh);
+    if value.is_changed() && !fresh {
+        setting.pending = Some(Duration::ZERO)
+    } else if let Some(pending) = &mut setting.pending {
+        *pending += time.delta()
+    }
+
+    if setting.save.as_ref().is_some_and(Task::is_finished) {
+        setting.save = None
+    }
+
+    if setting.save.is_some() || setting.pending.is_none_or(|pending| pending < setting.debounce) {
+        return
+    }
+
+    setting.pending = None;
+
+    let file = setting.file;
+    let write = storage.write_setting(file, value.clone());
+    setting.save = Some(IoTaskPool::get().spawn(async move {
+        if let Err(e) = write.await {
+            error!("Couldn't write setting file `{file}`: {e}")
+        }
+    }))
+}
+
+/// Finishes the write in progress and writes pending changes before the app exits, blocking until
+/// they're on disk. On the web, where the main thread can't block, they're left to finish in the
+/// background instead, which they do as long as the page stays open.
+fn flush_setting<T: Persist + Resource>(storage: Res<LocalStorage>, mut setting: ResMut<SettingFile<T>>, value: Res<T>) {
+    let save = setting.save.take();
+    let write = setting.pending.take().map(|_| {
+        let file = setting.file;
+        let write = storage.write_setting(file, value.clone());
+        async move {
+            if let Err(e) = write.await {
+                error!("Couldn't write setting file `{file}`: {e}")
+            }
+        }
+    });
+
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        if let Some(save) = save {
+            block_on(save)
+        }
+
+        if let Some(write) = write {
+            block_on(write)
+        }
+    }
+
+    // Writes to the same file are queued, so the pending changes still land after the one in progress.
+    #[cfg(target_arch = "wasm32")]
+    {
+        if let Some(save) = save {
+            save.detach()
+        }
+
+        if let Some(write) = write {
+            IoTaskPool::get().spawn(write).detach()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread, time::Duration};
+
+    use bevy::{
+        tasks::{TaskPool, block_on},
+        time::{TimePlugin, TimeUpdateStrategy},
+    };
+
+    use super::*;
+    use crate::{InputKeyboardPref, MemoryBackend, open_envelope, seal_envelope};
+
+    fn is_loaded(app: &App) -> bool {
+        app.world().resource::<SettingFile<InputKeyboardPref>>().is_loaded()
+    }
+
+    fn is_pending(app: &App) -> bool {
+        app.world().resource::<SettingFile<InputKeyboardPref>>().is_pending()
+    }
+
+    #[test]
+    fn loads_and_saves() {
+        IoTaskPool::get_or_init(TaskPool::default);
+        let storage = LocalStorage::at("memory").with_backend(Arc::new(MemoryBackend::default()));
+        let read = || block_on(storage.read_setting::<InputKeyboardPref>("keyboard.pref")).unwrap();
+
+        let saved = InputKeyboardPref {
+            jump: KeyCode::KeyK,
+            ..default()
+        };
+        block_on(storage.write_setting("keyboard.pref", saved)).unwrap();
+
+        let mut app = App::new();
+        app.add_plugins((TaskPoolPlugin::default(), TimePlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)))
+            .insert_resource(LocalStorage::at("memory").with_backend(storage.backend.clone()))
+            .register_setting::<InputKeyboardPref>("keyboard.pref");
+
+        for _ in 0..100 {
+            app.update();
+            if is_loaded(&app) {
+                break
+            }
+
+            thread::sleep(Duration::from_millis(10))
+        }
+
+        assert!(is_loaded(&app));
+        assert!(!is_pending(&app), "loading isn't a change to write back");
+        assert_eq!(app.world().resource::<InputKeyboardPref>().jump, KeyCode::KeyK);
+
+        app.world_mut().resource_mut::<InputKeyboardPref>().dash = KeyCode::KeyL;
+        app.update();
+        app.world_mut().resource_mut::<InputKeyboardPref>().dash = KeyCode::KeyM;
+        app.update();
+        assert!(is_pending(&app));
+        assert_eq!(read().dash, KeyCode::ShiftLeft, "still within the debounce");
+
+        for _ in 0..10 {
+            app.update()
+        }
+        assert!(!is_pending(&app));
+
+        let mut written = None;
+        for _ in 0..100 {
+            let pref = read();
+            if pref.dash == KeyCode::KeyM {
+                written = Some(pref);
+                break
+            }
+
+            thread::sleep(Duration::from_millis(10))
+        }
+        assert_eq!(written.map(|pref| pref.jump), Some(KeyCode::KeyK));
+
+        // Changes still within the debounce are written before exiting.
+        app.world_mut().resource_mut::<InputKeyboardPref>().primary = KeyCode::KeyU;
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+        assert_eq!(read().primary, KeyCode::KeyU);
+    }
+
+    #[test]
+    fn flushes_write_in_progress() {
+        let storage = LocalStorage::at("memory").with_backend(Arc::new(MemoryBackend::default()));
+        let read = || block_on(storage.read_setting::<InputKeyboardPref>("keyboard.pref")).unwrap();
+
+        let mut app = App::new();
+        app.add_plugins((TaskPoolPlugin::default(), TimePlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(100)))
+            .insert_resource(LocalStorage::at("memory").with_backend(storage.backend.clone()))
+            .register_setting::<InputKeyboardPref>("keyboard.pref");
+
+        for _ in 0..100 {
+            app.update();
+            if is_loaded(&app) {
+                break
+            }
+
+            thread::sleep(Duration::from_millis(10))
+        }
+
+        // No debounce, so the write starts right away, and exit in the same frame before it's done.
+        app.world_mut().resource_mut::<SettingFile<InputKeyboardPref>>().debounce = Duration::ZERO;
+        app.world_mut().resource_mut::<InputKeyboardPref>().dash = KeyCode::KeyL;
+        app.world_mut().send_event(AppExit::Success);
+        app.update();
+
+        assert!(!app.world().resource::<SettingFile<InputKeyboardPref>>().is_saving());
+        assert_eq!(read().dash, KeyCode::KeyL);
+    }
+
+    #[test]
+    fn upgrades_legacy_files() {
+        IoTaskPool::get_or_init(TaskPool::default);
+        let storage = LocalStorage::at("memory").with_backend(Arc::new(MemoryBackend::default()));
+        let path = storage.dir(Storage::Settings).join("keyboard.pref");
+        let magic = Storage::Settings.magic();
+
+        // Files written before envelopes were introduced hold just the payload.
+        let saved = InputKeyboardPref {
+            jump: KeyCode::KeyK,
+            ..default()
+        };
+        block_on(storage.write_setting("keyboard.pref", saved)).unwrap();
+        let payload = open_envelope(magic, block_on(storage.backend.read(&path)).unwrap()).unwrap();
+        block_on(storage.backend.write(&path, payload.clone())).unwrap();
+
+        let read = block_on(storage.read_setting::<InputKeyboardPref>("keyboard.pref")).unwrap();
+        assert_eq!(read.jump, KeyCode::KeyK);
+
+        block_on(storage.writes().wait_for(&path));
+        let bytes = block_on(storage.backend.read(&path)).unwrap();
+        assert_eq!(open_envelope(magic, bytes).unwrap(), payload);
+
+        // Anything else that fails to verify is still reported as corrupt.
+        let mut corrupt = seal_envelope(magic, &payload).unwrap();
+        *corrupt.last_mut().unwrap() ^= 1;
+        block_on(storage.backend.write(&path, corrupt)).unwrap();
+        let e = block_on(storage.read_setting::<InputKeyboardPref>("keyboard.pref")).unwrap_err();
+        assert_eq!(e.kind(), IoErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,431 @@
+use std::{
+    cmp::Reverse,
+    collections::VecDeque,
+    io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult},
+    path::{Path, PathBuf},
+    pin::pin,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{IoTaskPool, Task, futures_lite::io::Cursor},
+    utils::{BoxedFuture, ConditionalSendFuture, futures::check_ready},
+};
+
+use crate::{
+    LocalStorage, Storage, StorageBackend, StorageEntry, StoragePath,
+    persist::{Persist, PersistReader},
+    r, read_with_backup, seal_envelope, w,
+};
+
+/// File holding a slot's [`SlotMeta`], written last so its presence marks the slot as complete.
+pub const SLOT_META: &str = "meta.save";
+/// File holding a slot's game-specific payload.
+pub const SLOT_DATA: &str = "data.save";
+
+/// Summary of a save slot, stored apart from the payload so slots can be listed without decoding
+/// it.
+#[derive(Persist, Clone, Debug)]
+#[persist(version = 0, auto)]
+pub struct SlotMeta {
+    /// Display name, which may differ from the slot's directory name.
+    pub name: String,
+    /// Seconds since the Unix epoch at which the slot was last written.
+    pub timestamp: u64,
+    pub playtime: Duration,
+    /// Version of the game that wrote the slot.
+    pub version: String,
+}
+
+impl SlotMeta {
+    /// Creates metadata timestamped now and tagged with the running game version.
+    pub fn new(name: impl Into<String>, playtime: Duration) -> Self {
+        Self {
+            name: name.into(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|time| time.as_secs())
+                .unwrap_or_default(),
+            playtime,
+            version: env!("CARGO_PKG_VERSION").into(),
+        }
+    }
+}
+
+impl LocalStorage {
+    fn slot_dir(&self, slot: &str) -> IoResult<PathBuf> {
+        Ok(self.saves_dir.join(StoragePath::name(slot)?))
+    }
+
+    fn slot_file(slot: &str, file: &str) -> IoResult<StoragePath> {
+        Ok(StoragePath::name(slot)?.join(file)?)
+    }
+
+    /// Lists every complete slot, most recently written first. Slots whose metadata can't be read
+    /// are skipped with a warning.
+    pub fn list_slots(&self) -> impl ConditionalSendFuture<Output = IoResult<Vec<(String, SlotMeta)>>> + use<> {
+        let saves_dir = self.saves_dir.clone();
+        let backend = self.backend.clone();
+        async move {
+            let entries = match backend.list(&saves_dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == IoErrorKind::NotFound => return Ok(Vec::new()),
+                Err(e) => return Err(e),
+            };
+
+            let mut slots = Vec::new();
+            for StorageEntry { name: slot, is_dir } in entries {
+                if !is_dir {
+                    continue
+                }
+
+                if let Err(e) = StoragePath::name(&slot) {
+                    warn!("Skipping save slot with invalid name: {e}");
+                    continue
+                }
+
+                let meta = async {
+                    let path = saves_dir.join(&slot).join(SLOT_META);
+                    let payload = read_with_backup(backend.clone(), path, Storage::Saves.magic()).await?;
+                    let mut r = pin!(PersistReader::new(Cursor::new(payload)));
+                    r!(r, SlotMeta)
+                };
+
+                match meta.await {
+                    Ok(meta) => slots.push((slot, meta)),
+                    Err(e) => warn!("Couldn't read save slot `{slot}`: {e}"),
+                }
+            }
+
+            slots.sort_by_key(|(.., meta)| Reverse(meta.timestamp));
+            Ok(slots)
+        }
+    }
+
+    pub fn read_slot_meta(&self, slot: &str) -> impl ConditionalSendFuture<Output = IoResult<SlotMeta>> + use<> {
+        let reader = Self::slot_file(slot, SLOT_META).map(|file| self.reader(Storage::Saves, file));
+        async move {
+            let mut r = pin!(reader?.await?);
+            r!(r, SlotMeta)
+        }
+    }
+
+    pub fn read_slot<T: Persist>(&self, slot: &str) -> impl ConditionalSendFuture<Output = IoResult<T>> + use<T> {
+        let reader = Self::slot_file(slot, SLOT_DATA).map(|file| self.reader(Storage::Saves, file));
+        async move {
+            let mut r = pin!(reader?.await?);
+            r!(r, T)
+        }
+    }
+
+    /// Creates or overwrites `slot`.
+    pub fn write_slot<T: Persist>(
+        &self,
+        slot: &str,
+        meta: SlotMeta,
+        data: T,
+    ) -> impl ConditionalSendFuture<Output = IoResult<()>> + use<T> {
+        let writers = Self::slot_file(slot, SLOT_DATA).and_then(|data| {
+            let meta = Self::slot_file(slot, SLOT_META)?;
+            Ok((self.writer(Storage::Saves, data), self.writer(Storage::Saves, meta)))
+        });
+
+        async move {
+            let (data_writer, meta_writer) = writers?;
+            let mut w = pin!(data_writer.await?);
+            w!(w, T: data)?;
+            w.close().await?;
+
+            let mut w = pin!(meta_writer.await?);
+            w!(w, SlotMeta: meta)?;
+            w.close().await
+        }
+    }
+
+    /// Copies `from` into a new slot `to`, failing if `to` already exists. Writes to `from` still
+    /// queued are waited for, so the copy has everything written before it was requested.
+    pub fn copy_slot(&self, from: &str, to: &str) -> impl ConditionalSendFuture<Output = IoResult<()>> + use<> {
+        let dirs = self.slot_dir(from).and_then(|from| Ok((from, self.slot_dir(to)?)));
+        let (backend, writes) = (self.backend.clone(), self.writes.clone());
+        async move {
+            let (from, to) = dirs?;
+            writes.wait_for(&from).await;
+            writes.wait_for(&to).await;
+            ensure_vacant(&*backend, &to).await?;
+
+            // Metadata last, so the copy only shows up as a slot once complete.
+            let magic = Storage::Saves.magic();
+            for file in [SLOT_DATA, SLOT_META] {
+                let payload = read_with_backup(backend.clone(), from.join(file), magic).await?;
+                let bytes = seal_envelope(magic, &payload)?;
+                writes.write(&backend, to.join(file), bytes).wait().await?;
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Renames `from` to `to`, failing if `to` already exists. Writes to either slot still queued
+    /// are waited for, so they can't bring `from` back afterwards.
+    pub fn rename_slot(&self, from: &str, to: &str) -> impl ConditionalSendFuture<Output = IoResult<()>> + use<> {
+        let dirs = self.slot_dir(from).and_then(|from| Ok((from, self.slot_dir(to)?)));
+        let (backend, writes) = (self.backend.clone(), self.writes.clone());
+        async move {
+            let (from, to) = dirs?;
+            writes.wait_for(&from).await;
+            writes.wait_for(&to).await;
+            ensure_vacant(&*backend, &to).await?;
+            backend.rename(&from, &to).await
+        }
+    }
+
+    /// Deletes `slot`. Writes to it still queued are waited for, so they can't bring it back
+    /// afterwards.
+    pub fn delete_slot(&self, slot: &str) -> impl ConditionalSendFuture<Output = IoResult<()>> + use<> {
+        let dir = self.slot_dir(slot);
+        let (backend, writes) = (self.backend.clone(), self.writes.clone());
+        async move {
+            let dir = dir?;
+            writes.wait_for(&dir).await;
+            backend.delete(&dir).await
+        }
+    }
+}
+
+async fn ensure_vacant(backend: &dyn StorageBackend, path: &Path) -> IoResult<()> {
+    if backend.exists(path).await? {
+        return Err(IoError::new(
+            IoErrorKind::AlreadyExists,
+            format!("Save slot already exists: {}", path.display()),
+        ))
+    }
+
+    Ok(())
+}
+
+/// Slot listing kept up to date by [`SlotRequest`]s.
+#[derive(Resource, Default, Debug)]
+pub struct SaveSlots {
+    slots: Vec<(String, SlotMeta)>,
+}
+
+impl SaveSlots {
+    /// Slot names and metadata, most recently written first.
+    pub fn slots(&self) -> &[(String, SlotMeta)] {
+        &self.slots
+    }
+}
+
+/// Asks the storage to manage slots in the background. Requests run one at a time in the order they
+/// were sent, so later ones may depend on earlier ones, e.g. renaming a slot that was just copied.
+/// [`SaveSlots`] is refreshed after each, and the outcome is reported as a [`SlotResponse`].
+///
+/// Slots are written with [`LocalStorage::write_slot`] instead, since their payload's type is up to
+/// the game. Wait for the [`SlotResponse`]s of requests touching a slot before writing it.
+#[derive(Event, Clone, Debug)]
+pub enum SlotRequest {
+    Refresh,
+    Copy { from: String, to: String },
+    Rename { from: String, to: String },
+    Delete(String),
+}
+
+#[derive(Event, Debug)]
+pub struct SlotResponse {
+    pub request: SlotRequest,
+    pub result: IoResult<()>,
+}
+
+type SlotTask = Task<(SlotRequest, IoResult<()>, IoResult<Vec<(String, SlotMeta)>>)>;
+
+/// Requests waiting for the one in progress, if any, to finish.
+#[derive(Default)]
+pub(crate) struct SlotQueue {
+    pending: VecDeque<(SlotRequest, BoxedFuture<'static, IoResult<()>>)>,
+    running: Option<SlotTask>,
+}
+
+pub(crate) fn handle_slot_requests(
+    storage: Res<LocalStorage>,
+    mut slots: ResMut<SaveSlots>,
+    mut requests: EventReader<SlotRequest>,
+    mut responses: EventWriter<SlotResponse>,
+    mut queue: Local<SlotQueue>,
+) {
+    for request in requests.read() {
+        let op: BoxedFuture<'static, IoResult<()>> = match request {
+            SlotRequest::Refresh => Box::pin(async { Ok(()) }),
+            SlotRequest::Copy { from, to } => Box::pin(storage.copy_slot(from, to)),
+            SlotRequest::Rename { from, to } => Box::pin(storage.rename_slot(from, to)),
+            SlotRequest::Delete(slot) => Box::pin(storage.delete_slot(slot)),
+        };
+
+        queue.pending.push_back((request.clone(), op))
+    }
+
+    loop {
+        if let Some(task) = &mut queue.running {
+            if !task.is_finished() {
+                break
+            }
+
+            let (request, result, list) = check_ready(task).expect("`is_finished()` implies Poll::Ready");
+            queue.running = None;
+
+            match list {
+                Ok(list) => slots.slots = list,
+                Err(e) => error!("Couldn't list save slots: {e}"),
+            }
+
+            responses.send(SlotResponse { request, result });
+        }
+
+        // Only start the next request once the previous one is done, as it may depend on it.
+        let Some((request, op)) = queue.pending.pop_front() else { break };
+        let list = storage.list_slots();
+        queue.running = Some(IoTaskPool::get().spawn(async move {
+            let result = op.await;
+            (request, result, list.await)
+        }))
+    }
+}
+
+pub(crate) fn refresh_slots(mut requests: EventWriter<SlotRequest>) {
+    requests.send(SlotRequest::Refresh);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, process, sync::Arc, thread};
+
+    use async_fs::remove_dir_all;
+    use bevy::tasks::{TaskPool, block_on};
+
+    use super::*;
+    use crate::{MemoryBackend, WriteOutcome};
+
+    fn manage_slots(storage: &LocalStorage) {
+        block_on(async {
+            assert!(storage.list_slots().await.unwrap().is_empty());
+
+            storage
+                .write_slot("first", SlotMeta::new("First", Duration::from_secs(60)), vec![1u8, 2, 3])
+                .await
+                .unwrap();
+            storage.copy_slot("first", "second").await.unwrap();
+            assert_eq!(
+                storage.copy_slot("first", "second").await.unwrap_err().kind(),
+                IoErrorKind::AlreadyExists
+            );
+
+            storage.rename_slot("second", "third").await.unwrap();
+            storage.delete_slot("first").await.unwrap();
+
+            let slots = storage.list_slots().await.unwrap();
+            assert_eq!(slots.len(), 1);
+            assert_eq!(slots[0].0, "third");
+            assert_eq!(slots[0].1.name, "First");
+            assert_eq!(storage.read_slot::<Vec<u8>>("third").await.unwrap(), [1, 2, 3]);
+
+            for name in ["../escape", "/tmp/escape", "third/data.save", "CON"] {
+                let e = storage.copy_slot("third", name).await.unwrap_err();
+                assert!(
+                    matches!(e.kind(), IoErrorKind::InvalidInput | IoErrorKind::InvalidFilename),
+                    "{name}: {e}"
+                );
+            }
+
+            assert_eq!(storage.delete_slot("..").await.unwrap_err().kind(), IoErrorKind::InvalidInput);
+            assert_eq!(storage.list_slots().await.unwrap().len(), 1);
+        })
+    }
+
+    #[test]
+    fn manage_slots_on_disk() {
+        IoTaskPool::get_or_init(TaskPool::default);
+        let root = temp_dir().join(format!("centripetal-slots-{}", process::id()));
+        manage_slots(&LocalStorage::at(&root));
+        block_on(remove_dir_all(&root)).unwrap();
+    }
+
+    #[test]
+    fn handles_dependent_requests() {
+        let storage = LocalStorage::at("memory").with_backend(Arc::new(MemoryBackend::default()));
+        block_on(storage.write_slot("first", SlotMeta::new("First", Duration::ZERO), 0u8)).unwrap();
+
+        let mut app = App::new();
+        app.add_plugins(TaskPoolPlugin::default())
+            .insert_resource(storage)
+            .init_resource::<SaveSlots>()
+            .add_event::<SlotRequest>()
+            .add_event::<SlotResponse>()
+            .add_systems(Update, handle_slot_requests);
+
+        // Each request depends on the one before it having finished.
+        app.world_mut().send_event_batch([
+            SlotRequest::Copy {
+                from: "first".into(),
+                to: "second".into(),
+            },
+            SlotRequest::Rename {
+                from: "second".into(),
+                to: "third".into(),
+            },
+            SlotRequest::Delete("first".into()),
+        ]);
+
+        let mut cursor = app.world().resource::<Events<SlotResponse>>().get_cursor();
+        let mut responses = Vec::new();
+        for _ in 0..100 {
+            app.update();
+            let events = app.world().resource::<Events<SlotResponse>>();
+            responses.extend(
+                cursor
+                    .read(events)
+                    .map(|response| response.result.as_ref().map_err(|e| e.kind()).copied()),
+            );
+            if responses.len() == 3 {
+                break
+            }
+
+            thread::sleep(Duration::from_millis(10))
+        }
+
+        assert_eq!(responses, [Ok(()); 3]);
+        let slots = app.world().resource::<SaveSlots>().slots();
+        assert_eq!(slots.len(), 1);
+        assert_eq!(slots[0].0, "third");
+    }
+
+    #[test]
+    fn deletes_after_queued_writes() {
+        IoTaskPool::get_or_init(TaskPool::default);
+        let backend = MemoryBackend::default();
+        let storage = LocalStorage::at("memory").with_backend(Arc::new(backend.clone()));
+        let dir = storage.slot_dir("first").unwrap();
+
+        // Queued but not waited for, as if the slot was being saved when the delete was requested.
+        let bytes = seal_envelope(Storage::Saves.magic(), &[]).unwrap();
+        let writes =
+            [SLOT_DATA, SLOT_META].map(|file| storage.writes.write(&storage.backend, dir.join(file), bytes.clone()));
+
+        block_on(async {
+            storage.delete_slot("first").await.unwrap();
+            for write in writes {
+                assert_eq!(write.wait().await.unwrap(), WriteOutcome::Written);
+            }
+
+            assert!(!backend.exists(&dir).await.unwrap());
+            assert!(storage.list_slots().await.unwrap().is_empty());
+        })
+    }
+
+    #[test]
+    fn manage_slots_in_memory() {
+        IoTaskPool::get_or_init(TaskPool::default);
+        let backend = MemoryBackend::default();
+        manage_slots(&LocalStorage::at("memory").with_backend(Arc::new(backend.clone())));
+        assert!(block_on(backend.exists(Path::new("memory/saves/third/meta.save"))).unwrap());
+    }
+}